@@ -0,0 +1,39 @@
+//! Coordinate trait
+
+use core::fmt::Debug;
+use ndarray::Ix;
+use num_traits::{AsPrimitive, Euclid, NumAssignOps, PrimInt, Signed};
+
+/// Scalar type of the coordinates of the cells
+pub trait Coordinate:
+    PrimInt + Signed + NumAssignOps + Euclid + AsPrimitive<isize> + Debug + Send + Sync
+{
+    /// Convert an offset or a length along a chunk.
+    fn from_ix(ix: Ix) -> Self;
+
+    /// Convert a non-negative offset or length along a chunk.
+    fn to_ix(self) -> Ix;
+}
+
+/// Implement Coordinate trait for the signed integer types
+macro_rules! impl_coordinate {
+    ($($t:ty),*) => {
+        $(
+            impl Coordinate for $t {
+                #[inline]
+                fn from_ix(ix: Ix) -> Self {
+                    debug_assert!(<$t>::try_from(ix).is_ok(), "{ix} does not fit in {}", stringify!($t));
+                    ix as $t
+                }
+
+                #[inline]
+                fn to_ix(self) -> Ix {
+                    debug_assert!(Ix::try_from(self).is_ok(), "{self} does not fit in an offset");
+                    self as Ix
+                }
+            }
+        )*
+    };
+}
+
+impl_coordinate!(i8, i16, i32, i64, i128, isize);
@@ -12,28 +12,85 @@ pub mod iterator;
 /// Bounding box
 pub mod bounding_box;
 
-use crate::{Chunk, cell::Cell};
+/// Storage of the cells of a chunk
+pub mod storage;
+
+/// Guard to mutate a cell without allocating chunks needlessly
+pub mod guard;
+
+/// Entry API to access a cell of the GridMap
+pub mod entry;
+
+/// Errors raised when accessing the cells of the GridMap
+pub mod error;
+
+/// Chunk stored in the GridMap along with its number of non-null cells
+mod slot;
+
+/// Conversion between regions of the GridMap and dense arrays
+pub mod dense;
+
+/// Change the dimensions of the chunks of the GridMap
+pub mod rechunk;
+
+/// Difference between two GridMaps which can be applied and reverted
+pub mod patch;
+
+/// Undo and redo the changes made to a GridMap
+pub mod history;
+
+/// Immutable snapshots of the GridMap sharing its chunks
+pub mod snapshot;
+
+/// Compact binary format of the GridMap
+pub mod binary;
+
+/// Serialization of the GridMap
+#[cfg(feature = "serde")]
+mod serialize;
+
+/// Standard traits implemented by the GridMap
+mod traits;
+
+use crate::{Chunk, cell::Cell, coordinate::Coordinate};
+use bounding_box::{BoundingBox, extents::Extents};
 use hashbrown::HashMap;
+use iterator::order::ChunkOrder;
 use ndarray::{Array, Dim, Dimension, IntoDimension, Ix};
+use num_traits::NumCast;
+use slot::Slot;
 
-/// GridMap of cells
-pub struct GridMap<A, const D: usize, Ic = isize>
+/// GridMap of cells, indexed by chunk keys of type `Ic` and cell coordinates of type `C`
+#[derive(Clone)]
+pub struct GridMap<A, const D: usize, Ic = isize, C = isize>
 where
     A: Cell,
 {
     /// Dimensions of the chunks in the gridmap
     chunk_dim: [Ix; D],
 
+    /// Base 2 logarithm of the chunk dimensions if they are all powers of two
+    shift: Option<[u32; D]>,
+
     // TODO: check if the array should be boxed or not
     /// Internal data
-    map: HashMap<[Ic; D], Chunk<A, D>>,
+    map: HashMap<[Ic; D], Slot<A, D>>,
 
     /// Empty cell for out-of-bound access
     empty: A,
+
+    /// Order in which the iterators visit the chunks
+    order: Option<ChunkOrder>,
+
+    /// Extents of the chunks, if they are tracked
+    extents: Option<Extents<D, C>>,
+
+    /// Limits of the cell indices, if the gridmap is bounded
+    limits: Option<BoundingBox<D, C>>,
 }
 
 /// Create a new empty GridMap
-impl<A, const D: usize, Ic> Default for GridMap<A, D, Ic>
+impl<A, const D: usize, Ic, C> Default for GridMap<A, D, Ic, C>
 where
     A: Cell,
 {
@@ -41,38 +98,149 @@ where
     fn default() -> Self {
         Self {
             chunk_dim: [12; D],
+            shift: chunk_shift(&[12; D]),
             map: HashMap::new(),
             empty: A::NULL,
+            order: None,
+            extents: None,
+            limits: None,
         }
     }
 }
 
-impl<A, const D: usize, Ic> GridMap<A, D, Ic>
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
 where
     A: Cell,
 {
-    /// Create a new empty GridMap
+    /// Create a new empty GridMap.
+    /// Only chunks of at most `binary::MAX_CHUNK_LENGTH` cells can be decoded from the binary format.
+    /// Panics if a chunk dimension does not fit in the coordinate type.
     #[inline]
-    pub fn new(chunk_dim: [Ix; D]) -> Self {
+    pub fn new(chunk_dim: [Ix; D]) -> Self
+    where
+        C: Coordinate,
+    {
+        check_chunk_dim::<D, C>(&chunk_dim);
         Self {
             chunk_dim,
+            shift: chunk_shift(&chunk_dim),
             map: HashMap::new(),
             empty: A::NULL,
+            order: None,
+            extents: None,
+            limits: None,
         }
     }
 
-    /// Create a new GridMap with a predefined capacity
-    pub fn with_capacity(chunk_dim: [Ix; D], capacity: usize) -> Self {
+    /// Create a new GridMap with a predefined capacity.
+    /// Panics if a chunk dimension does not fit in the coordinate type.
+    pub fn with_capacity(chunk_dim: [Ix; D], capacity: usize) -> Self
+    where
+        C: Coordinate,
+    {
+        check_chunk_dim::<D, C>(&chunk_dim);
         Self {
             chunk_dim,
+            shift: chunk_shift(&chunk_dim),
             map: HashMap::with_capacity(capacity),
             empty: A::NULL,
+            order: None,
+            extents: None,
+            limits: None,
         }
     }
+
+    /// Dimensions of the chunks in the gridmap
+    #[inline]
+    pub fn chunk_dim(&self) -> &[Ix; D] {
+        &self.chunk_dim
+    }
+
+    /// Returns true if every chunk dimension is a power of two,
+    /// indexes are then split with shifts and masks instead of divisions.
+    #[inline]
+    pub fn has_power_of_two_chunks(&self) -> bool {
+        self.shift.is_some()
+    }
+
+    /// Limits of the cell indices, `None` if the gridmap is unbounded
+    #[inline]
+    pub fn limits(&self) -> Option<&BoundingBox<D, C>> {
+        self.limits.as_ref()
+    }
+
+    /// Bound the cell indices accessed one at a time.
+    /// Accessing a cell outside of the limits panics, or fails with the fallible accessors.
+    /// The cells already stored outside of the limits are kept.
+    #[inline]
+    pub fn set_limits(&mut self, limits: Option<BoundingBox<D, C>>) {
+        self.limits = limits;
+    }
+}
+
+/// Returns true if every chunk dimension fits in the coordinate type
+#[inline]
+fn chunk_dim_fits<const D: usize, C>(chunk_dim: &[Ix; D]) -> bool
+where
+    C: Coordinate,
+{
+    chunk_dim
+        .iter()
+        .all(|&dim| <C as NumCast>::from(dim).is_some())
+}
+
+/// Panics if a chunk dimension does not fit in the coordinate type,
+/// the cells would otherwise be indexed with wrapped offsets
+#[inline]
+fn check_chunk_dim<const D: usize, C>(chunk_dim: &[Ix; D])
+where
+    C: Coordinate,
+{
+    assert!(
+        chunk_dim_fits::<D, C>(chunk_dim),
+        "chunk dimensions {chunk_dim:?} do not fit in the coordinate type"
+    );
+}
+
+/// Base 2 logarithm of the chunk dimensions if they are all powers of two
+#[inline]
+fn chunk_shift<const D: usize>(chunk_dim: &[Ix; D]) -> Option<[u32; D]> {
+    chunk_dim
+        .iter()
+        .all(|dim| dim.is_power_of_two())
+        .then(|| chunk_dim.map(Ix::trailing_zeros))
+}
+
+/// Convert a chunk coordinate to the type of the chunk keys.
+/// Panics if the coordinate is out of range of the key type.
+#[inline]
+fn chunk_key_component<Ic, C>(c: C) -> Ic
+where
+    Ic: TryFrom<isize>,
+    C: Coordinate,
+{
+    c.to_isize()
+        .and_then(|c| Ic::try_from(c).ok())
+        .unwrap_or_else(|| panic!("chunk coordinate {c:?} is out of range of the chunk key type"))
+}
+
+/// Convert chunk coordinates to a chunk key,
+/// `None` if they are out of range of the key type, in which case no such chunk can exist.
+#[inline]
+fn try_chunk_key<const D: usize, Ic, C>(key: &[C; D]) -> Option<[Ic; D]>
+where
+    Ic: TryFrom<isize>,
+    C: Coordinate,
+{
+    let chunk_index = key.map(|c| c.to_isize().and_then(|c| Ic::try_from(c).ok()));
+    chunk_index
+        .iter()
+        .all(Option::is_some)
+        .then(|| chunk_index.map(Option::unwrap))
 }
 
 /// Build a chunk with the given dimensions
-fn make_chunk<A, const D: usize>(chunk_dim: &[Ix; D]) -> Array<A, Dim<[Ix; D]>>
+fn make_chunk<A, const D: usize>(chunk_dim: &[Ix; D]) -> Chunk<A, D>
 where
     A: Default,
     [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
@@ -1,107 +1,294 @@
 //! Basic operations available on the GridMap
 
-use super::GridMap;
-use crate::{Chunk, cell::Cell, gridmap::make_chunk, util::is_chunk_empty};
-use core::{hash::Hash, ops::IndexMut};
+use super::{
+    GridMap, bounding_box::BoundingBox, chunk_key_component, error::GridMapError,
+    iterator::from_chunk_to_cell_index, slot::Slot, storage::Storage,
+};
+use crate::{cell::Cell, coordinate::Coordinate};
+use core::hash::Hash;
+use hashbrown::hash_map::Entry;
 use ndarray::{Dim, Dimension, IntoDimension, Ix};
-use num_traits::{AsPrimitive, ConstZero};
+use num_traits::{AsPrimitive, ConstZero, ToPrimitive};
 
 /// Access a cell in the gridmap
-impl<A, const D: usize, Ic> GridMap<A, D, Ic>
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
 where
     A: Cell,
+    C: Coordinate,
 {
     pub fn get<I>(&self, index: &[I; D]) -> A
     where
         A: Clone,
-        Ic: Eq + Hash + ConstZero + From<isize>,
-        I: AsPrimitive<isize>,
+        Ic: Eq + Hash + ConstZero + TryFrom<isize>,
+        I: ToPrimitive + Copy,
         [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
         Dim<[Ix; D]>: Dimension,
     {
         let (chunk_index, cell_index) = self.split_index(index);
         self.index_chunk_cell(&chunk_index, &cell_index).clone()
     }
+
+    /// Get the cell at the given index,
+    /// fails if the index overflows the coordinate types or lies outside of the limits.
+    pub fn try_get<I>(&self, index: &[I; D]) -> Result<A, GridMapError>
+    where
+        A: Clone,
+        Ic: Eq + Hash + ConstZero + TryFrom<isize>,
+        I: ToPrimitive + Copy,
+        [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        let (chunk_index, cell_index) = self.try_split_index(index)?;
+        Ok(self.index_chunk_cell(&chunk_index, &cell_index).clone())
+    }
 }
 
 /// Set a cell in the gridmap
-impl<A, const D: usize, Ic> GridMap<A, D, Ic>
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
 where
     A: Cell,
+    C: Coordinate,
 {
     pub fn set<I>(&mut self, index: &[I; D], cell: A)
     where
-        A: Default,
-        Ic: Eq + Hash + ConstZero + From<isize>,
-        I: AsPrimitive<isize>,
+        A: PartialEq + Clone,
+        Ic: Eq + Hash + ConstZero + TryFrom<isize> + AsPrimitive<isize>,
+        I: ToPrimitive + Copy,
         [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
         Dim<[Ix; D]>: Dimension,
     {
         // index of the chunk and index of the cell inside of the chunk
         let (chunk_index, cell_index) = self.split_index(index);
+        self.set_chunk_cell(chunk_index, &cell_index, cell);
+    }
 
+    /// Set the cell at the given index,
+    /// fails if the index overflows the coordinate types or lies outside of the limits.
+    pub fn try_set<I>(&mut self, index: &[I; D], cell: A) -> Result<(), GridMapError>
+    where
+        A: PartialEq + Clone,
+        Ic: Eq + Hash + ConstZero + TryFrom<isize> + AsPrimitive<isize>,
+        I: ToPrimitive + Copy,
+        [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        let (chunk_index, cell_index) = self.try_split_index(index)?;
+        self.set_chunk_cell(chunk_index, &cell_index, cell);
+        Ok(())
+    }
+
+    /// Set a cell knowing chunk index and cell index
+    pub fn set_chunk_cell(&mut self, chunk_index: [Ic; D], cell_index: &Dim<[Ix; D]>, cell: A)
+    where
+        A: PartialEq + Clone,
+        Ic: Eq + Hash + AsPrimitive<isize>,
+        [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+        Dim<[Ix; D]>: Dimension,
+    {
         if cell.is_null() {
             // remove a cell in the chunk
             // if the chunk does not exists, there is nothing to do
             if let Some(chunk) = self.map.get_mut(&chunk_index) {
-                let ptr = chunk.index_mut(cell_index);
-                *ptr = cell;
+                chunk.set(cell_index, cell);
+                if let Some(extents) = &mut self.extents {
+                    extents.set_cell(&self.chunk_dim, &self.shift, &chunk_index, cell_index, true);
+                }
 
                 // if the chunk end up empty, remove it from the map
-                if is_chunk_empty(chunk) {
+                if chunk.occupied() == 0 {
                     self.map.remove(&chunk_index);
+                    if let Some(extents) = &mut self.extents {
+                        extents.remove_chunk(&chunk_index);
+                    }
                 }
             }
         } else {
             // add a new cell in the chunk
             // if the chunk does not exists, create it
-            let chunk = self
-                .map
-                .entry(chunk_index)
-                .or_insert_with(|| make_chunk::<A, D>(&self.chunk_dim));
+            if let Some(extents) = &mut self.extents {
+                extents.set_cell(
+                    &self.chunk_dim,
+                    &self.shift,
+                    &chunk_index,
+                    cell_index,
+                    false,
+                );
+            }
+            let chunk = match self.map.entry(chunk_index) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    if let Some(extents) = &mut self.extents {
+                        extents.insert_chunk(entry.key());
+                    }
+                    entry.insert(Slot::empty(Storage::new(&self.chunk_dim)))
+                }
+            };
 
             // set the cell
-            let ptr = chunk.index_mut(cell_index);
-            *ptr = cell;
+            chunk.set(cell_index, cell);
         }
     }
 }
 
-impl<A, const D: usize, Ic> GridMap<A, D, Ic>
+/// Fill a region of the gridmap
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
 where
     A: Cell,
+    C: Coordinate,
+{
+    /// Set every cell within the bounding box to the given cell.
+    /// Chunks entirely covered by the box are stored as a uniform value without any array.
+    pub fn fill(&mut self, bounds: &BoundingBox<D, C>, cell: A)
+    where
+        A: PartialEq + Clone,
+        Ic: Eq + Hash + ConstZero + TryFrom<isize> + AsPrimitive<isize>,
+        [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        // find the range of chunks covered by the bounding box
+        if bounds.is_empty() {
+            return;
+        }
+        let range = bounds.chunk_range(&self.chunk_dim);
+
+        for key in range.indices() {
+            // compute the index of the chunk and the part of it covered by the box
+            let chunk_index: [Ic; D] = key.map(chunk_key_component);
+            let origin: [C; D] = from_chunk_to_cell_index(&self.chunk_dim, &self.shift, &key);
+            let mut start = [0; D];
+            let mut size = [0; D];
+            let mut covered = true;
+            for d in 0..D {
+                let dim = C::from_ix(self.chunk_dim[d]);
+                let s = (bounds.start[d] - origin[d]).max(C::zero());
+                let e = (bounds.end[d] - origin[d]).min(dim);
+
+                start[d] = s.to_ix();
+                size[d] = (e - s).to_ix();
+                covered &= s.is_zero() && e == dim;
+            }
+
+            // a chunk fully covered can be replaced entirely
+            if covered {
+                if cell.is_null() {
+                    if self.map.remove(&chunk_index).is_some()
+                        && let Some(extents) = &mut self.extents
+                    {
+                        extents.touch_chunk(&self.chunk_dim, &self.shift, &chunk_index);
+                        extents.remove_chunk(&chunk_index);
+                    }
+                } else {
+                    if let Some(extents) = &mut self.extents {
+                        if !self.map.contains_key(&chunk_index) {
+                            extents.insert_chunk(&chunk_index);
+                        }
+                        extents.touch_chunk(&self.chunk_dim, &self.shift, &chunk_index);
+                    }
+                    let chunk = Slot::new(Storage::uniform(&self.chunk_dim, cell.clone()));
+                    self.map.insert(chunk_index, chunk);
+                }
+                continue;
+            }
+
+            // there is nothing to erase in a chunk which does not exist
+            let mut entry = match self.map.entry(chunk_index) {
+                Entry::Occupied(entry) => entry,
+                Entry::Vacant(_) if cell.is_null() => continue,
+                Entry::Vacant(entry) => {
+                    if let Some(extents) = &mut self.extents {
+                        extents.insert_chunk(entry.key());
+                    }
+                    entry.insert_entry(Slot::empty(Storage::new(&self.chunk_dim)))
+                }
+            };
+            if let Some(extents) = &mut self.extents {
+                extents.touch_chunk(&self.chunk_dim, &self.shift, entry.key());
+            }
+            let chunk = entry.get_mut();
+
+            for local in ndarray::indices(size) {
+                let local = local.into_dimension();
+                let mut cell_index = start;
+                for d in 0..D {
+                    cell_index[d] += local[d];
+                }
+                chunk.set(&Dim(cell_index), cell.clone());
+            }
+
+            // if the chunk end up empty, remove it from the map
+            if chunk.occupied() == 0 {
+                if let Some(extents) = &mut self.extents {
+                    extents.remove_chunk(entry.key());
+                }
+                entry.remove();
+            }
+        }
+    }
+}
+
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
+where
+    A: Cell,
+    C: Coordinate,
 {
     /// Access a chunk
     #[inline]
-    pub fn get_chunk<I>(&self, chunk_index: &[Ic; D]) -> Option<&Chunk<A, D>>
+    pub fn get_chunk<I>(&self, chunk_index: &[Ic; D]) -> Option<&Storage<A, D>>
     where
         Ic: Eq + Hash,
         Dim<[Ix; D]>: Dimension,
     {
-        self.map.get(chunk_index)
+        self.map.get(chunk_index).map(Slot::storage)
     }
 
-    /// Access a chunk as mutable
+    /// Access a chunk as mutable, copying its cells if a snapshot shares them.
+    /// Its cells will have to be counted again the next time it is needed.
     #[inline]
-    pub fn get_chunk_mut<I>(&mut self, chunk_index: &[Ic; D]) -> Option<&mut Chunk<A, D>>
+    pub fn get_chunk_mut<I>(&mut self, chunk_index: &[Ic; D]) -> Option<&mut Storage<A, D>>
     where
-        Ic: Eq + Hash,
+        A: Clone,
+        Ic: Eq + Hash + AsPrimitive<isize>,
         Dim<[Ix; D]>: Dimension,
     {
-        self.map.get_mut(chunk_index)
+        if let Some(extents) = &mut self.extents {
+            extents.touch_chunk(&self.chunk_dim, &self.shift, chunk_index);
+        }
+        self.map.get_mut(chunk_index).map(Slot::storage_mut)
+    }
+
+    /// Number of non-null cells in the gridmap.
+    /// The chunks modified through mutable references are counted again once.
+    #[inline]
+    pub fn len(&self) -> usize
+    where
+        Dim<[Ix; D]>: Dimension,
+    {
+        self.map.values().map(Slot::occupied).sum()
+    }
+
+    /// Returns true if the gridmap contains only null cells
+    #[inline]
+    pub fn is_empty(&self) -> bool
+    where
+        Dim<[Ix; D]>: Dimension,
+    {
+        self.map.values().all(|chunk| chunk.occupied() == 0)
     }
 
     /// Check if the chunk at given chunk index should be freed
     pub fn try_free_chunk<I>(&mut self, chunk_index: &[Ic; D]) -> bool
     where
-        Ic: Eq + Hash,
+        Ic: Eq + Hash + AsPrimitive<isize>,
         Dim<[Ix; D]>: Dimension,
     {
         // if the chunk does not exists, there is nothing to do
-        if let Some(chunk) = self.map.get(chunk_index) {
+        if let Some(chunk) = self.map.get_mut(chunk_index) {
             // if the chunk end up empty, remove it from the map
-            if is_chunk_empty(chunk) {
+            if chunk.occupied() == 0 {
                 self.map.remove(chunk_index);
+                if let Some(extents) = &mut self.extents {
+                    extents.remove_chunk(chunk_index);
+                }
                 return true;
             }
         }
@@ -112,9 +299,27 @@ where
     #[inline]
     pub fn prune(&mut self)
     where
-        Ic: Eq + Hash,
+        Ic: Eq + Hash + AsPrimitive<isize>,
         Dim<[Ix; D]>: Dimension,
     {
-        self.map.retain(|_, chunk| !is_chunk_empty(chunk));
+        self.map.retain(|chunk_index, chunk| {
+            let keep = chunk.occupied() != 0;
+            if !keep && let Some(extents) = &mut self.extents {
+                extents.remove_chunk(chunk_index);
+            }
+            keep
+        });
+    }
+
+    /// Store each chunk in the representation using the least memory
+    pub fn compress(&mut self)
+    where
+        A: PartialEq + Clone,
+        [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        for chunk in self.map.values_mut() {
+            chunk.compress();
+        }
     }
 }
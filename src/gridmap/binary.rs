@@ -0,0 +1,549 @@
+//! Compact binary format of the GridMap
+//!
+//! The encoded data is laid out as follows, all numbers being little-endian:
+//! - header: magic bytes, format version, number of dimensions, cell type tag,
+//!   chunk dimensions and number of chunks,
+//! - for each chunk: the chunk index followed by the runs of identical cells
+//!   in the logical order of the chunk, each run being a length and a cell,
+//! - a checksum of everything written before it.
+
+use super::{GridMap, chunk_dim_fits, iterator::order::Chunks, slot::Slot, storage::Storage};
+use crate::{Chunk, cell::Cell, coordinate::Coordinate, util::fnv1a};
+use alloc::vec::Vec;
+use core::{fmt, hash::Hash};
+use ndarray::{Array, Dim, Dimension, IntoDimension, Ix};
+use num_traits::{AsPrimitive, ConstZero};
+
+/// Magic bytes at the start of the encoded data
+const MAGIC: [u8; 4] = *b"GMAP";
+
+/// Version of the binary format
+const VERSION: u16 = 1;
+
+/// Largest number of cells in a chunk accepted when decoding
+pub const MAX_CHUNK_LENGTH: usize = 1 << 24;
+
+/// Cell which can be written in the binary format
+pub trait BinaryCell: Cell + PartialEq + Sized {
+    /// Tag identifying the type of cell in the header
+    const TAG: u8;
+
+    /// Number of bytes used to encode a cell
+    const SIZE: usize;
+
+    /// Append the bytes of the cell to the buffer
+    fn write_bytes(&self, buffer: &mut Vec<u8>);
+
+    /// Read a cell from exactly `Self::SIZE` bytes
+    fn read_bytes(bytes: &[u8]) -> Self;
+}
+
+/// Implement the BinaryCell trait for numeric types
+macro_rules! impl_binary_cell {
+    ($($t:ty => $tag:expr),* $(,)?) => {
+        $(
+            impl BinaryCell for $t {
+                const TAG: u8 = $tag;
+                const SIZE: usize = size_of::<$t>();
+
+                #[inline]
+                fn write_bytes(&self, buffer: &mut Vec<u8>) {
+                    buffer.extend_from_slice(&self.to_le_bytes());
+                }
+
+                #[inline]
+                fn read_bytes(bytes: &[u8]) -> Self {
+                    let mut raw = [0; size_of::<$t>()];
+                    raw.copy_from_slice(bytes);
+                    <$t>::from_le_bytes(raw)
+                }
+            }
+        )*
+    };
+}
+
+impl_binary_cell! {
+    u8 => 1, u16 => 2, u32 => 3, u64 => 4, u128 => 5,
+    i8 => 6, i16 => 7, i32 => 8, i64 => 9, i128 => 10,
+    f32 => 11, f64 => 12,
+}
+
+/// Error raised when decoding a GridMap
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DecodeError {
+    /// The data ended before the GridMap was complete
+    Truncated,
+
+    /// The data does not start with the expected magic bytes
+    InvalidMagic,
+
+    /// The data was written with an unknown version of the format
+    UnsupportedVersion(u16),
+
+    /// The data describes a GridMap with another number of dimensions
+    DimensionMismatch { expected: usize, found: usize },
+
+    /// The data describes a GridMap with another type of cell
+    CellTypeMismatch { expected: u8, found: u8 },
+
+    /// A chunk dimension is null or too large
+    InvalidChunkDim,
+
+    /// A chunk index does not fit in the chunk index type
+    InvalidChunkIndex,
+
+    /// The same chunk index appears twice
+    DuplicateChunk,
+
+    /// The runs of a chunk do not match the number of cells in a chunk
+    InvalidRunLength,
+
+    /// There are bytes remaining after the checksum
+    TrailingBytes,
+
+    /// The checksum does not match the data
+    ChecksumMismatch,
+}
+
+/// Describe the error
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated => write!(f, "unexpected end of data"),
+            Self::InvalidMagic => write!(f, "data is not an encoded gridmap"),
+            Self::UnsupportedVersion(version) => write!(f, "unsupported format version {version}"),
+            Self::DimensionMismatch { expected, found } => {
+                write!(f, "expected {expected} dimensions, found {found}")
+            }
+            Self::CellTypeMismatch { expected, found } => {
+                write!(f, "expected cell type {expected}, found {found}")
+            }
+            Self::InvalidChunkDim => write!(f, "invalid chunk dimensions"),
+            Self::InvalidChunkIndex => write!(f, "chunk index out of range"),
+            Self::DuplicateChunk => write!(f, "duplicate chunk index"),
+            Self::InvalidRunLength => write!(f, "runs do not match the chunk size"),
+            Self::TrailingBytes => write!(f, "unexpected data after the checksum"),
+            Self::ChecksumMismatch => write!(f, "checksum mismatch"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+/// Encode the GridMap in the binary format
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
+where
+    A: Cell,
+{
+    /// Encode the GridMap into a compact binary buffer.
+    /// Gridmaps whose chunks hold more than `MAX_CHUNK_LENGTH` cells are encoded
+    /// but cannot be decoded.
+    pub fn encode(&self) -> Vec<u8>
+    where
+        A: BinaryCell,
+        Ic: AsPrimitive<isize>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        // only write the chunks containing some cells, in a defined order
+        let chunks: Vec<_> = Chunks::new(&self.map, self.sorted_order())
+            .filter(|(_, chunk)| chunk.occupied() != 0)
+            .collect();
+
+        // header
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(&MAGIC);
+        buffer.extend_from_slice(&VERSION.to_le_bytes());
+        buffer.push(D as u8);
+        buffer.push(A::TAG);
+        for dim in self.chunk_dim {
+            buffer.extend_from_slice(&(dim as u64).to_le_bytes());
+        }
+        buffer.extend_from_slice(&(chunks.len() as u64).to_le_bytes());
+
+        for (chunk_index, chunk) in chunks {
+            for index in chunk_index {
+                buffer.extend_from_slice(&(index.as_() as i64).to_le_bytes());
+            }
+            write_runs(&mut buffer, chunk.storage());
+        }
+
+        // checksum over everything written so far
+        let checksum = fnv1a(&buffer);
+        buffer.extend_from_slice(&checksum.to_le_bytes());
+        buffer
+    }
+
+    /// Decode a GridMap from a buffer created with `encode`.
+    /// Chunks holding more than `MAX_CHUNK_LENGTH` cells,
+    /// or with a dimension which does not fit in the coordinate type, are rejected as invalid.
+    pub fn decode(bytes: &[u8]) -> Result<Self, DecodeError>
+    where
+        A: BinaryCell + Clone,
+        C: Coordinate,
+        Ic: Eq + Hash + ConstZero + TryFrom<isize>,
+        [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        // separate the checksum from the data it covers
+        if bytes.len() < size_of::<u32>() {
+            return Err(DecodeError::Truncated);
+        }
+        let (data, checksum) = bytes.split_at(bytes.len() - size_of::<u32>());
+
+        let mut reader = Reader(data);
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(DecodeError::InvalidMagic);
+        }
+        let version = reader.read_u16()?;
+        if version != VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        if fnv1a(data).to_le_bytes() != checksum {
+            return Err(DecodeError::ChecksumMismatch);
+        }
+
+        // header
+        let dims = reader.read_u8()? as usize;
+        if dims != D {
+            return Err(DecodeError::DimensionMismatch {
+                expected: D,
+                found: dims,
+            });
+        }
+        let tag = reader.read_u8()?;
+        if tag != A::TAG {
+            return Err(DecodeError::CellTypeMismatch {
+                expected: A::TAG,
+                found: tag,
+            });
+        }
+        let mut chunk_dim = [0; D];
+        for dim in chunk_dim.iter_mut() {
+            *dim = Ix::try_from(reader.read_u64()?)
+                .ok()
+                .filter(|&dim| dim != 0)
+                .ok_or(DecodeError::InvalidChunkDim)?;
+        }
+        let length = chunk_dim
+            .iter()
+            .try_fold(1, |acc: Ix, &dim| acc.checked_mul(dim))
+            .filter(|&length| length <= MAX_CHUNK_LENGTH && chunk_dim_fits::<D, C>(&chunk_dim))
+            .ok_or(DecodeError::InvalidChunkDim)?;
+        let count = reader.read_u64()?;
+
+        // chunks
+        let mut gridmap = Self::new(chunk_dim);
+        for _ in 0..count {
+            let mut chunk_index = [Ic::ZERO; D];
+            for index in chunk_index.iter_mut() {
+                *index = isize::try_from(reader.read_i64()?)
+                    .ok()
+                    .and_then(|index| Ic::try_from(index).ok())
+                    .ok_or(DecodeError::InvalidChunkIndex)?;
+            }
+
+            let chunk = read_runs::<A, D>(&mut reader, chunk_dim, length)?;
+            let chunk = Slot::new(Storage::Dense(chunk));
+            if gridmap.map.insert(chunk_index, chunk).is_some() {
+                return Err(DecodeError::DuplicateChunk);
+            }
+        }
+
+        if reader.0.is_empty() {
+            Ok(gridmap)
+        } else {
+            Err(DecodeError::TrailingBytes)
+        }
+    }
+}
+
+/// Write the cells of the chunk as runs of identical cells
+fn write_runs<A, const D: usize>(buffer: &mut Vec<u8>, chunk: &Storage<A, D>)
+where
+    A: BinaryCell,
+    Dim<[Ix; D]>: Dimension,
+{
+    let mut cells = chunk.iter();
+    let Some(mut current) = cells.next() else {
+        return;
+    };
+    let mut run = 1_u32;
+
+    for cell in cells {
+        if cell == current && run < u32::MAX {
+            run += 1;
+        } else {
+            buffer.extend_from_slice(&run.to_le_bytes());
+            current.write_bytes(buffer);
+            current = cell;
+            run = 1;
+        }
+    }
+    buffer.extend_from_slice(&run.to_le_bytes());
+    current.write_bytes(buffer);
+}
+
+/// Read runs of identical cells until the chunk is filled
+fn read_runs<A, const D: usize>(
+    reader: &mut Reader<'_>,
+    chunk_dim: [Ix; D],
+    length: usize,
+) -> Result<Chunk<A, D>, DecodeError>
+where
+    A: BinaryCell + Clone,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    // each run holds at least one cell, do not trust the length further than the input
+    let mut cells = Vec::with_capacity(length.min(reader.0.len() / (4 + A::SIZE)));
+    while cells.len() < length {
+        let run = reader.read_u32()? as usize;
+        if run == 0 || run > length - cells.len() {
+            return Err(DecodeError::InvalidRunLength);
+        }
+        let cell = A::read_bytes(reader.take(A::SIZE)?);
+        cells.resize(cells.len() + run, cell);
+    }
+
+    // the cells are stored in logical order, thus in standard layout
+    Array::from_shape_vec(chunk_dim, cells).map_err(|_| DecodeError::InvalidChunkDim)
+}
+
+/// Read little-endian values from a buffer
+struct Reader<'b>(&'b [u8]);
+
+impl<'b> Reader<'b> {
+    /// Take the next bytes from the buffer
+    fn take(&mut self, count: usize) -> Result<&'b [u8], DecodeError> {
+        if self.0.len() < count {
+            return Err(DecodeError::Truncated);
+        }
+        let (bytes, rest) = self.0.split_at(count);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    /// Read an array of bytes
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let mut raw = [0; N];
+        raw.copy_from_slice(self.take(N)?);
+        Ok(raw)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        self.read_array().map(u8::from_le_bytes)
+    }
+
+    fn read_u16(&mut self) -> Result<u16, DecodeError> {
+        self.read_array().map(u16::from_le_bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, DecodeError> {
+        self.read_array().map(u32::from_le_bytes)
+    }
+
+    fn read_u64(&mut self) -> Result<u64, DecodeError> {
+        self.read_array().map(u64::from_le_bytes)
+    }
+
+    fn read_i64(&mut self) -> Result<i64, DecodeError> {
+        self.read_array().map(i64::from_le_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gridmap::bounding_box::BoundingBox;
+
+    /// Append the checksum to the data
+    fn seal(mut data: Vec<u8>) -> Vec<u8> {
+        let checksum = fnv1a(&data);
+        data.extend_from_slice(&checksum.to_le_bytes());
+        data
+    }
+
+    /// Header of a 2D gridmap of `u8`
+    fn header(chunk_dim: [u64; 2], count: u64) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&MAGIC);
+        data.extend_from_slice(&VERSION.to_le_bytes());
+        data.extend_from_slice(&[2, u8::TAG]);
+        for dim in chunk_dim {
+            data.extend_from_slice(&dim.to_le_bytes());
+        }
+        data.extend_from_slice(&count.to_le_bytes());
+        data
+    }
+
+    /// Chunk of a 2D gridmap filled with a single run
+    fn chunk(data: &mut Vec<u8>, index: [i64; 2], run: u32, cell: u8) {
+        for i in index {
+            data.extend_from_slice(&i.to_le_bytes());
+        }
+        data.extend_from_slice(&run.to_le_bytes());
+        data.push(cell);
+    }
+
+    fn sample() -> GridMap<u8, 2> {
+        let mut gridmap = GridMap::new([4, 4]);
+        gridmap.set(&[1, 2], 3);
+        gridmap.set(&[-7, 5], 9);
+        gridmap.set(&[-7, 6], 9);
+        gridmap.fill(
+            &BoundingBox {
+                start: [8, -8],
+                end: [16, 0],
+            },
+            4,
+        );
+        gridmap.compress();
+        gridmap
+    }
+
+    #[test]
+    fn round_trip() {
+        let gridmap = sample();
+        let decoded = GridMap::<u8, 2>::decode(&gridmap.encode()).unwrap();
+        assert_eq!(decoded, gridmap);
+        assert_eq!(decoded.chunk_dim(), &[4, 4]);
+
+        let empty = GridMap::<f32, 3>::new([2, 3, 4]);
+        assert_eq!(GridMap::<f32, 3>::decode(&empty.encode()).unwrap(), empty);
+    }
+
+    #[test]
+    fn chunk_length_limit() {
+        let mut gridmap = GridMap::<u8, 2>::new([1 << 12, 1 << 12]);
+        gridmap.set(&[-1, 5], 1);
+        let decoded = GridMap::<u8, 2>::decode(&gridmap.encode()).unwrap();
+        assert_eq!(decoded.get(&[-1, 5]), 1);
+        assert_eq!(decoded.len(), 1);
+
+        let mut data = header([(1 << 12) + 1, 1 << 12], 1);
+        chunk(&mut data, [0, 0], 1, 1);
+        assert_eq!(
+            GridMap::<u8, 2>::decode(&seal(data)),
+            Err(DecodeError::InvalidChunkDim)
+        );
+    }
+
+    #[test]
+    fn chunk_dim_overflows_coordinates() {
+        let bytes = GridMap::<u8, 2>::new([4, 200]).encode();
+        assert_eq!(
+            GridMap::<u8, 2, isize, i8>::decode(&bytes),
+            Err(DecodeError::InvalidChunkDim)
+        );
+        assert!(GridMap::<u8, 2, isize, i16>::decode(&bytes).is_ok());
+    }
+
+    #[test]
+    fn header_mismatch() {
+        let mut bytes = sample().encode();
+        assert_eq!(
+            GridMap::<u8, 3>::decode(&bytes),
+            Err(DecodeError::DimensionMismatch {
+                expected: 3,
+                found: 2
+            })
+        );
+        assert_eq!(
+            GridMap::<u16, 2>::decode(&bytes),
+            Err(DecodeError::CellTypeMismatch {
+                expected: u16::TAG,
+                found: u8::TAG
+            })
+        );
+
+        bytes[4] = 7;
+        assert_eq!(
+            GridMap::<u8, 2>::decode(&bytes),
+            Err(DecodeError::UnsupportedVersion(7))
+        );
+        bytes[0] = b'X';
+        assert_eq!(
+            GridMap::<u8, 2>::decode(&bytes),
+            Err(DecodeError::InvalidMagic)
+        );
+    }
+
+    #[test]
+    fn corrupt_data() {
+        let mut bytes = sample().encode();
+        let last = bytes.len() - 5;
+        bytes[last] ^= 1;
+        assert_eq!(
+            GridMap::<u8, 2>::decode(&bytes),
+            Err(DecodeError::ChecksumMismatch)
+        );
+        assert_eq!(GridMap::<u8, 2>::decode(&[]), Err(DecodeError::Truncated));
+    }
+
+    #[test]
+    fn truncated_data() {
+        let bytes = sample().encode();
+        let data = bytes[..bytes.len() - 5].to_vec();
+        assert_eq!(
+            GridMap::<u8, 2>::decode(&seal(data)),
+            Err(DecodeError::Truncated)
+        );
+    }
+
+    #[test]
+    fn trailing_bytes() {
+        let bytes = sample().encode();
+        let mut data = bytes[..bytes.len() - 4].to_vec();
+        data.push(0);
+        assert_eq!(
+            GridMap::<u8, 2>::decode(&seal(data)),
+            Err(DecodeError::TrailingBytes)
+        );
+    }
+
+    #[test]
+    fn duplicate_chunk() {
+        let mut data = header([2, 2], 2);
+        chunk(&mut data, [1, -1], 4, 5);
+        chunk(&mut data, [1, -1], 4, 6);
+        assert_eq!(
+            GridMap::<u8, 2>::decode(&seal(data)),
+            Err(DecodeError::DuplicateChunk)
+        );
+    }
+
+    #[test]
+    fn invalid_runs() {
+        let mut data = header([2, 2], 1);
+        chunk(&mut data, [0, 0], 5, 1);
+        assert_eq!(
+            GridMap::<u8, 2>::decode(&seal(data)),
+            Err(DecodeError::InvalidRunLength)
+        );
+
+        let mut data = header([2, 2], 1);
+        chunk(&mut data, [0, 0], 0, 1);
+        assert_eq!(
+            GridMap::<u8, 2>::decode(&seal(data)),
+            Err(DecodeError::InvalidRunLength)
+        );
+    }
+
+    #[test]
+    fn invalid_chunk_dim() {
+        let data = header([0, 4], 0);
+        assert_eq!(
+            GridMap::<u8, 2>::decode(&seal(data)),
+            Err(DecodeError::InvalidChunkDim)
+        );
+
+        // too many cells to allocate
+        let mut data = header([1 << 31, 1 << 31], 1);
+        chunk(&mut data, [0, 0], u32::MAX, 1);
+        assert_eq!(
+            GridMap::<u8, 2>::decode(&seal(data)),
+            Err(DecodeError::InvalidChunkDim)
+        );
+    }
+}
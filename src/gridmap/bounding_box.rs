@@ -1,40 +1,59 @@
 //! Bounding box module
 
-use crate::{cell::Cell, gridmap::GridMap, transform::Transform};
-use core::{hash::Hash, ops::IndexMut};
+use crate::{cell::Cell, coordinate::Coordinate, gridmap::GridMap, transform::Transform};
+use core::hash::Hash;
+use indices::Indices;
 use ndarray::{Dim, Dimension, IntoDimension, Ix};
 use num_traits::{AsPrimitive, ConstZero};
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 /// Compute the boundaries of the gridmap
 pub mod boundaries;
 
-/// Boundaries
-#[derive(PartialEq, Eq, Clone, Copy)]
-pub struct BoundingBox<const D: usize> {
+/// Iterator over all the indices of a bounding box
+pub mod indices;
+
+/// Incrementally maintained extents of the gridmap
+pub(crate) mod extents;
+
+/// Boundaries, the start is inclusive and the end is exclusive
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "[C; D]: Serialize",
+        deserialize = "[C; D]: Deserialize<'de>"
+    ))
+)]
+pub struct BoundingBox<const D: usize, C = isize> {
     /// starting point of the box
-    pub start: [isize; D],
+    pub start: [C; D],
 
-    /// ending point of the box
-    pub end: [isize; D],
+    /// ending point of the box (exclusive)
+    pub end: [C; D],
 }
 
-impl<A, const D: usize, Ic> GridMap<A, D, Ic>
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
 where
     A: Cell,
+    C: Coordinate,
 {
     /// Copy a portion of the source gridmap to the target gridmap with the given transformation
-    pub fn copy_to(&self, target: &mut Self, transforms: &[&dyn Transform<D>])
+    pub fn copy_to(&self, target: &mut Self, transforms: &[&dyn Transform<D, C>])
     where
-        A: Default + Copy,
-        Ic: Eq + Hash + ConstZero + From<isize> + AsPrimitive<isize>,
+        A: PartialEq + Clone,
+        Ic: Eq + Hash + ConstZero + TryFrom<isize> + AsPrimitive<isize>,
         [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
         Dim<[Ix; D]>: Dimension,
     {
-        // Transform the indexes and apply to the target.
-        for (index, cell) in self.indexed_iter() {
+        // Transform the indexes and apply to the target,
+        // in a defined order so that overlapping cells always resolve the same way.
+        for (index, cell) in self.indexed_iter_in(self.sorted_order()) {
             let index = transforms.transform(&index);
-            let ptr = target.index_mut(index);
-            *ptr = *cell;
+            target.set(&index, cell.clone());
         }
 
         // since the empty cells are ignored, we are only adding more cells
@@ -45,20 +64,19 @@ where
     pub fn copy_to_within(
         &self,
         target: &mut Self,
-        transforms: &[&dyn Transform<D>],
-        bounding_box: &BoundingBox<D>,
+        transforms: &[&dyn Transform<D, C>],
+        bounding_box: &BoundingBox<D, C>,
     ) where
-        A: Default + Copy,
-        Ic: Eq + Hash + ConstZero + From<isize> + AsPrimitive<isize>,
+        A: PartialEq + Clone,
+        Ic: Eq + Hash + ConstZero + TryFrom<isize> + AsPrimitive<isize>,
         [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
         Dim<[Ix; D]>: Dimension,
     {
         // For each cell in the bounded source gridmap,
         // transform the indexes and apply to the target.
-        for (index, cell) in self.bounded_iter(*bounding_box) {
+        for (index, cell) in self.bounded_iter_in(*bounding_box, self.sorted_order()) {
             let index = transforms.transform(&index);
-            let ptr = target.index_mut(index);
-            *ptr = *cell;
+            target.set(&index, cell.clone());
         }
 
         // since the empty cells are ignored, we are only adding more cells
@@ -66,9 +84,34 @@ where
     }
 }
 
-impl<const D: usize> BoundingBox<D> {
+impl<const D: usize, C> BoundingBox<D, C>
+where
+    C: Coordinate,
+{
+    /// Create the smallest bounding box containing all the points.
+    /// Returns `None` if there are no points.
+    /// The end saturates at `C::MAX`, which therefore cannot be contained.
+    pub fn from_points<P>(points: P) -> Option<Self>
+    where
+        P: IntoIterator<Item = [C; D]>,
+    {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        let mut bounds = Self {
+            start: first,
+            end: first.map(|i| i.saturating_add(C::one())),
+        };
+        for point in points {
+            for (d, &i) in point.iter().enumerate() {
+                bounds.start[d] = bounds.start[d].min(i);
+                bounds.end[d] = bounds.end[d].max(i.saturating_add(C::one()));
+            }
+        }
+        Some(bounds)
+    }
+
     /// Check if the index is inside the specified boundaries
-    pub fn contains(&self, index: &[isize; D]) -> bool {
+    pub fn contains(&self, index: &[C; D]) -> bool {
         for (d, &i) in index.iter().enumerate() {
             if !(self.start[d] <= i && i < self.end[d]) {
                 return false;
@@ -77,13 +120,229 @@ impl<const D: usize> BoundingBox<D> {
         true
     }
 
-    /// Check if the two bounding boxes overlap
+    /// Check if the two bounding boxes share at least one index,
+    /// boxes which only touch each other do not overlap.
     pub fn overlaps_with(&self, other: &Self) -> bool {
         for d in 0..D {
-            if !(self.start[d] <= other.end[d] && other.start[d] <= self.end[d]) {
+            if !(self.start[d] < other.end[d] && other.start[d] < self.end[d]) {
                 return false;
             }
         }
         true
     }
+
+    /// Returns true if the bounding box does not contain any index
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        (0..D).any(|d| self.end[d] <= self.start[d])
+    }
+
+    /// Number of indices along each dimension,
+    /// saturating at `usize::MAX` for lengths which do not fit.
+    pub fn size(&self) -> [usize; D] {
+        let mut size = [0; D];
+        for (d, s) in size.iter_mut().enumerate() {
+            *s = self.length(d).unwrap_or(usize::MAX);
+        }
+        size
+    }
+
+    /// Number of indices in the bounding box, saturating at `usize::MAX`
+    #[inline]
+    pub fn volume(&self) -> usize {
+        self.checked_volume().unwrap_or(usize::MAX)
+    }
+
+    /// Number of indices in the bounding box, `None` if it does not fit in a `usize`
+    pub fn checked_volume(&self) -> Option<usize> {
+        if self.is_empty() {
+            return Some(0);
+        }
+        (0..D).try_fold(1_usize, |volume, d| volume.checked_mul(self.length(d)?))
+    }
+
+    /// Number of indices along the dimension, `None` if it does not fit in a `usize`
+    #[inline]
+    fn length(&self, d: usize) -> Option<usize> {
+        if self.end[d] <= self.start[d] {
+            return Some(0);
+        }
+        match self.end[d].checked_sub(&self.start[d]) {
+            Some(length) => length.to_usize(),
+            // the start is negative and the end is not, count both sides of zero
+            None => {
+                let below = (-(self.start[d] + C::one())).to_usize()?;
+                self.end[d].to_usize()?.checked_add(below)?.checked_add(1)
+            }
+        }
+    }
+
+    /// Smallest bounding box containing both bounding boxes,
+    /// empty bounding boxes are ignored.
+    pub fn union(&self, other: &Self) -> Self {
+        if self.is_empty() {
+            return *other;
+        }
+        if other.is_empty() {
+            return *self;
+        }
+
+        let mut bounds = *self;
+        for d in 0..D {
+            bounds.start[d] = self.start[d].min(other.start[d]);
+            bounds.end[d] = self.end[d].max(other.end[d]);
+        }
+        bounds
+    }
+
+    /// Bounding box of the indices contained in both bounding boxes.
+    /// Returns `None` if they do not overlap.
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let mut bounds = *self;
+        for d in 0..D {
+            bounds.start[d] = self.start[d].max(other.start[d]);
+            bounds.end[d] = self.end[d].min(other.end[d]);
+        }
+        if bounds.is_empty() {
+            None
+        } else {
+            Some(bounds)
+        }
+    }
+
+    /// Grow the bounding box by the given margin on every side,
+    /// a negative margin shrinks it. The limits saturate at the bounds of `C`.
+    pub fn expand(&self, margin: C) -> Self {
+        let mut bounds = *self;
+        for d in 0..D {
+            bounds.start[d] = bounds.start[d].saturating_sub(margin);
+            bounds.end[d] = bounds.end[d].saturating_add(margin);
+        }
+        bounds
+    }
+
+    /// Move the bounding box by the given offset,
+    /// the limits saturate at the bounds of `C`.
+    pub fn translate(&self, offset: &[C; D]) -> Self {
+        let mut bounds = *self;
+        for (d, &o) in offset.iter().enumerate() {
+            bounds.start[d] = bounds.start[d].saturating_add(o);
+            bounds.end[d] = bounds.end[d].saturating_add(o);
+        }
+        bounds
+    }
+
+    /// Range of the indices of the chunks overlapping the bounding box
+    pub fn chunk_range(&self, chunk_dim: &[Ix; D]) -> Self {
+        if self.is_empty() {
+            return Self {
+                start: [C::zero(); D],
+                end: [C::zero(); D],
+            };
+        }
+
+        let mut range = *self;
+        for (d, &dim) in chunk_dim.iter().enumerate() {
+            let dim = C::from_ix(dim);
+            range.start[d] = self.start[d].div_euclid(&dim);
+            range.end[d] = (self.end[d] - C::one()).div_euclid(&dim) + C::one();
+        }
+        range
+    }
+
+    /// Iterate over all the indices in the bounding box,
+    /// the last dimension varying the fastest.
+    #[inline]
+    pub fn indices(&self) -> Indices<D, C> {
+        Indices::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_and_volume() {
+        let bounds = BoundingBox {
+            start: [-2, 3],
+            end: [4, 5],
+        };
+        assert_eq!(bounds.size(), [6, 2]);
+        assert_eq!(bounds.volume(), 12);
+        assert_eq!(bounds.checked_volume(), Some(12));
+    }
+
+    #[test]
+    fn empty_volume() {
+        let bounds = BoundingBox {
+            start: [isize::MIN, 5],
+            end: [isize::MAX, 5],
+        };
+        assert_eq!(bounds.size(), [usize::MAX, 0]);
+        assert_eq!(bounds.checked_volume(), Some(0));
+    }
+
+    #[test]
+    fn wide_bounds_saturate() {
+        let bounds = BoundingBox {
+            start: [isize::MIN / 2; 2],
+            end: [isize::MAX / 2; 2],
+        };
+        assert_eq!(bounds.checked_volume(), None);
+        assert_eq!(bounds.volume(), usize::MAX);
+
+        let bounds = BoundingBox::<1, i8> {
+            start: [-100],
+            end: [100],
+        };
+        assert_eq!(bounds.size(), [200]);
+    }
+
+    #[test]
+    fn from_points_at_the_limits() {
+        let bounds = BoundingBox::<1, i32>::from_points([[i32::MAX]]).unwrap();
+        assert_eq!(bounds.end, [i32::MAX]);
+
+        let bounds = BoundingBox::<2, i32>::from_points([[i32::MIN, 0], [i32::MAX, 1]]).unwrap();
+        assert_eq!(bounds.start, [i32::MIN, 0]);
+        assert_eq!(bounds.end, [i32::MAX, 2]);
+        assert_eq!(bounds.size(), [u32::MAX as usize, 2]);
+    }
+
+    #[test]
+    fn expand_and_translate_saturate() {
+        let bounds = BoundingBox::<2, i8> {
+            start: [i8::MIN + 1, -3],
+            end: [i8::MAX - 1, 3],
+        };
+        assert_eq!(
+            bounds.expand(5),
+            BoundingBox {
+                start: [i8::MIN, -8],
+                end: [i8::MAX, 8],
+            }
+        );
+        assert_eq!(
+            bounds.expand(-5),
+            BoundingBox {
+                start: [i8::MIN + 6, 2],
+                end: [i8::MAX - 6, -2],
+            }
+        );
+        assert_eq!(
+            bounds.translate(&[100, -100]),
+            BoundingBox {
+                start: [-27, -103],
+                end: [i8::MAX, -97],
+            }
+        );
+        assert_eq!(
+            bounds.translate(&[-100, 100]),
+            BoundingBox {
+                start: [i8::MIN, 97],
+                end: [26, 103],
+            }
+        );
+    }
 }
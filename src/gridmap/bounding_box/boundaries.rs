@@ -1,18 +1,90 @@
 //! Compute the boundaries of the gridmap
 
 use super::BoundingBox;
-use crate::{cell::Cell, gridmap::GridMap};
+use crate::{
+    cell::Cell,
+    coordinate::Coordinate,
+    gridmap::{GridMap, iterator::from_chunk_to_cell_index, slot::Slot, try_chunk_key},
+};
+use alloc::vec::Vec;
+use core::hash::Hash;
 use ndarray::{Dim, Dimension, IntoDimension, Ix};
 use num_traits::AsPrimitive;
 
-impl<A, const D: usize, Ic> GridMap<A, D, Ic>
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
 where
     A: Cell,
+    C: Coordinate,
 {
-    /// Find the boundaries of the gridmap assuming empty chunks have been cleaned up.
-    pub fn boundaries(&self) -> BoundingBox<D>
+    /// Find the boundaries of the gridmap, the end being exclusive.
+    /// Returns `None` if the gridmap contains only null cells.
+    /// When the extents are tracked, only the chunks on the extreme slabs are visited,
+    /// unless there are more slab positions than chunks in the gridmap.
+    pub fn boundaries(&self) -> Option<BoundingBox<D, C>>
     where
         A: Clone,
+        Ic: Eq + Hash + TryFrom<isize> + AsPrimitive<isize>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        // When the extents are tracked, the extreme chunks are already known.
+        if let Some(extents) = &self.extents {
+            if let Some(bounds) = extents.cached() {
+                return bounds;
+            }
+            // The extreme chunks may have been emptied, scan everything in that case.
+            if let Some((chunk_0, chunk_1)) = extents.chunk_range() {
+                let bounds = match self.slabs(&chunk_0, &chunk_1) {
+                    Some(slabs) => {
+                        // look up the chunks on the slabs instead of scanning the gridmap
+                        let chunks = slabs
+                            .iter()
+                            .flat_map(BoundingBox::indices)
+                            .filter_map(|key| self.map.get_key_value(&try_chunk_key(&key)?));
+                        self.extreme_cells(&chunk_0, &chunk_1, chunks)
+                    }
+                    None => self.extreme_cells(&chunk_0, &chunk_1, self.map.iter()),
+                };
+                if bounds.is_some() {
+                    return bounds;
+                }
+            }
+        }
+
+        let (chunk_0, chunk_1) = self.extreme_chunks()?;
+        self.extreme_cells(&chunk_0, &chunk_1, self.map.iter())
+    }
+
+    /// Ranges of chunk indices lying on the extreme slabs along each dimension,
+    /// `None` if they hold more positions than the gridmap holds chunks.
+    fn slabs(&self, chunk_0: &[isize; D], chunk_1: &[isize; D]) -> Option<Vec<BoundingBox<D>>> {
+        let mut end = [0; D];
+        for d in 0..D {
+            end[d] = chunk_1[d].checked_add(1)?;
+        }
+
+        let mut slabs = Vec::new();
+        let mut count = 0_usize;
+        for d in 0..D {
+            for side in [chunk_0[d], chunk_1[d]] {
+                let mut slab = BoundingBox {
+                    start: *chunk_0,
+                    end,
+                };
+                slab.start[d] = side;
+                slab.end[d] = side + 1;
+                count = count.checked_add(slab.checked_volume()?)?;
+                slabs.push(slab);
+                if chunk_0[d] == chunk_1[d] {
+                    break;
+                }
+            }
+        }
+        (count < self.map.len()).then_some(slabs)
+    }
+
+    /// Find the extreme non-empty chunks along each dimension
+    fn extreme_chunks(&self) -> Option<([isize; D], [isize; D])>
+    where
         Ic: AsPrimitive<isize>,
         Dim<[Ix; D]>: Dimension,
     {
@@ -21,7 +93,9 @@ where
         let mut chunk_1 = [isize::MIN; D];
 
         // Iterate over the chunks to find the extreme points in chunk coordinates.
-        for (chunk_index, _) in self.map.iter() {
+        let mut found = false;
+        for (chunk_index, _) in self.map.iter().filter(|(_, chunk)| chunk.occupied() != 0) {
+            found = true;
             // For each dimension, check if the chunk is further away.
             // If so, register it as a new extreme point.
             for d in 0..D {
@@ -35,12 +109,33 @@ where
             }
         }
 
+        found.then_some((chunk_0, chunk_1))
+    }
+
+    /// Find the extreme cells in the extreme chunks along each dimension,
+    /// among the given chunks which must include every chunk on the extreme slabs.
+    /// Returns `None` if an extreme chunk does not contain any cell.
+    fn extreme_cells<'m>(
+        &self,
+        chunk_0: &[isize; D],
+        chunk_1: &[isize; D],
+        chunks: impl Iterator<Item = (&'m [Ic; D], &'m Slot<A, D>)>,
+    ) -> Option<BoundingBox<D, C>>
+    where
+        A: 'm,
+        Ic: AsPrimitive<isize> + 'm,
+        Dim<[Ix; D]>: Dimension,
+    {
         // Prepare the two points to find.
-        let mut cell_0 = [isize::MAX; D];
-        let mut cell_1 = [isize::MIN; D];
+        let mut cell_0 = [C::max_value(); D];
+        let mut cell_1 = [C::min_value(); D];
 
-        // Reiterate over the chunks but this time look of the extreme cells.
-        for (chunk_index, chunk) in self.map.iter() {
+        // Position of the first cell of the extreme chunks.
+        let origin_0: [C; D] = from_chunk_to_cell_index(&self.chunk_dim, &self.shift, chunk_0);
+        let origin_1: [C; D] = from_chunk_to_cell_index(&self.chunk_dim, &self.shift, chunk_1);
+
+        // Iterate over the chunks but this time look for the extreme cells.
+        for (chunk_index, chunk) in chunks.filter(|(_, chunk)| chunk.occupied() != 0) {
             // For each dimension, check if the chunk is an extreme one.
             // Check the cells in the chunk to find the extreme cell.
             for d in 0..D {
@@ -51,12 +146,12 @@ where
                 if c == l_0 {
                     // Prepare a reference to edit the cell and get the position of the chunk.
                     let p = &mut cell_0[d];
-                    let l = l_0 * self.chunk_dim[d] as isize;
+                    let l = origin_0[d];
 
                     // Iterate the cells to find a new extreme.
-                    for (i, a) in chunk.indexed_iter() {
+                    for (i, a) in chunk.storage().indexed_iter() {
                         if !a.is_null() {
-                            let i = i.into_dimension()[d] as isize + l;
+                            let i = C::from_ix(i.into_dimension()[d]) + l;
                             *p = i.min(*p);
                         }
                     }
@@ -67,12 +162,12 @@ where
                 if c == l_1 {
                     // Prepare a reference to edit the cell and get the position of the chunk.
                     let p = &mut cell_1[d];
-                    let l = l_1 * self.chunk_dim[d] as isize;
+                    let l = origin_1[d];
 
                     // Iterate the cells to find a new extreme.
-                    for (i, a) in chunk.indexed_iter() {
+                    for (i, a) in chunk.storage().indexed_iter() {
                         if !a.is_null() {
-                            let i = i.into_dimension()[d] as isize + l;
+                            let i = C::from_ix(i.into_dimension()[d]) + l;
                             *p = i.max(*p);
                         }
                     }
@@ -80,9 +175,79 @@ where
             }
         }
 
-        BoundingBox {
+        // Every extreme chunk must contain a cell.
+        if (0..D).any(|d| cell_0[d] > cell_1[d]) {
+            return None;
+        }
+
+        // The end is exclusive.
+        for p in &mut cell_1 {
+            *p += C::one();
+        }
+
+        Some(BoundingBox {
             start: cell_0,
             end: cell_1,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gridmap::{GridMap, bounding_box::BoundingBox};
+
+    fn sample(track: bool) -> GridMap<u8, 2> {
+        let mut gridmap = GridMap::new([4, 4]);
+        gridmap.track_boundaries(track);
+        for x in -20..20 {
+            for y in -3..40 {
+                gridmap.set(&[x, y], 1);
+            }
+        }
+        gridmap.set(&[25, 0], 2);
+        gridmap.set(&[0, -13], 3);
+        gridmap
+    }
+
+    #[test]
+    fn boundaries() {
+        let expected = Some(BoundingBox {
+            start: [-20, -13],
+            end: [26, 40],
+        });
+        assert_eq!(sample(false).boundaries(), expected);
+        assert_eq!(sample(true).boundaries(), expected);
+        assert_eq!(GridMap::<u8, 2>::new([4, 4]).boundaries(), None);
+    }
+
+    #[test]
+    fn emptied_extreme_chunk() {
+        for track in [false, true] {
+            let mut gridmap = sample(track);
+            gridmap.set(&[25, 0], 0);
+            gridmap.set(&[0, -13], 0);
+            assert_eq!(
+                gridmap.cached_boundaries(),
+                Some(BoundingBox {
+                    start: [-20, -3],
+                    end: [20, 40],
+                })
+            );
+
+            // the extreme chunk is kept but holds only null cells
+            gridmap.iter_mut().for_each(|cell| {
+                if *cell == 1 {
+                    *cell = 0
+                }
+            });
+            gridmap.set(&[3, 3], 5);
+            assert_eq!(
+                gridmap.boundaries(),
+                Some(BoundingBox {
+                    start: [3, 3],
+                    end: [4, 4],
+                })
+            );
         }
     }
 }
@@ -0,0 +1,208 @@
+//! Incrementally maintained extents of the gridmap
+
+use super::BoundingBox;
+use crate::{
+    cell::Cell,
+    coordinate::Coordinate,
+    gridmap::{GridMap, iterator::from_chunk_to_cell_index},
+};
+use alloc::collections::BTreeMap;
+use core::hash::Hash;
+use ndarray::{Dim, Dimension, Ix};
+use num_traits::AsPrimitive;
+
+/// Extents of the chunks of the gridmap along with its cached boundaries
+#[derive(Clone)]
+pub(crate) struct Extents<const D: usize, C> {
+    /// Number of chunks for each chunk coordinate along each dimension
+    chunks: [BTreeMap<isize, usize>; D],
+
+    /// Exact boundaries of the cells, `None` if they have to be computed again
+    cells: Option<Option<BoundingBox<D, C>>>,
+}
+
+impl<const D: usize, C> Extents<D, C> {
+    /// Register the chunks at the given indexes
+    pub(crate) fn new<'k, Ic, K>(keys: K) -> Self
+    where
+        Ic: AsPrimitive<isize>,
+        K: IntoIterator<Item = &'k [Ic; D]>,
+    {
+        let mut extents = Self {
+            chunks: core::array::from_fn(|_| BTreeMap::new()),
+            cells: None,
+        };
+        for key in keys {
+            extents.insert_chunk(key);
+        }
+        extents
+    }
+
+    /// Lowest and highest chunk coordinates along each dimension
+    pub(crate) fn chunk_range(&self) -> Option<([isize; D], [isize; D])> {
+        let mut low = [0; D];
+        let mut high = [0; D];
+        for (d, chunks) in self.chunks.iter().enumerate() {
+            low[d] = *chunks.first_key_value()?.0;
+            high[d] = *chunks.last_key_value()?.0;
+        }
+        Some((low, high))
+    }
+
+    /// Cached boundaries of the cells, `None` if they have to be computed again
+    #[inline]
+    pub(crate) fn cached(&self) -> Option<Option<BoundingBox<D, C>>>
+    where
+        C: Copy,
+    {
+        self.cells
+    }
+
+    /// Remember the boundaries of the cells
+    #[inline]
+    pub(crate) fn cache(&mut self, bounds: Option<BoundingBox<D, C>>) {
+        self.cells = Some(bounds);
+    }
+
+    /// The boundaries of the cells will have to be computed again
+    #[inline]
+    pub(crate) fn invalidate(&mut self) {
+        self.cells = None;
+    }
+
+    /// Register a new chunk
+    pub(crate) fn insert_chunk<Ic>(&mut self, key: &[Ic; D])
+    where
+        Ic: AsPrimitive<isize>,
+    {
+        for (d, chunks) in self.chunks.iter_mut().enumerate() {
+            *chunks.entry(key[d].as_()).or_default() += 1;
+        }
+    }
+
+    /// Unregister a chunk, its cells are expected to have been accounted for
+    pub(crate) fn remove_chunk<Ic>(&mut self, key: &[Ic; D])
+    where
+        Ic: AsPrimitive<isize>,
+    {
+        for (d, chunks) in self.chunks.iter_mut().enumerate() {
+            let c = key[d].as_();
+            if let Some(count) = chunks.get_mut(&c) {
+                *count -= 1;
+                if *count == 0 {
+                    chunks.remove(&c);
+                }
+            }
+        }
+    }
+
+    /// The cells of a chunk may have changed in any way,
+    /// the boundaries are kept only if the chunk lies strictly inside of them.
+    pub(crate) fn touch_chunk<Ic>(
+        &mut self,
+        chunk_dim: &[Ix; D],
+        shift: &Option<[u32; D]>,
+        key: &[Ic; D],
+    ) where
+        Ic: AsPrimitive<isize>,
+        C: Coordinate,
+    {
+        let Some(bounds) = self.cells else {
+            return;
+        };
+        let inside = bounds.is_some_and(|b| {
+            let origin: [C; D] = from_chunk_to_cell_index(chunk_dim, shift, key);
+            (0..D).all(|d| {
+                let start = origin[d];
+                let end = start.saturating_add(C::from_ix(chunk_dim[d]));
+                b.start[d] < start && end < b.end[d]
+            })
+        });
+        if !inside {
+            self.invalidate();
+        }
+    }
+
+    /// A cell has been set, the boundaries are extended to contain a non-null cell
+    /// and have to be computed again if a null cell was set on their border.
+    pub(crate) fn set_cell<Ic>(
+        &mut self,
+        chunk_dim: &[Ix; D],
+        shift: &Option<[u32; D]>,
+        key: &[Ic; D],
+        cell_index: &Dim<[Ix; D]>,
+        null: bool,
+    ) where
+        Ic: AsPrimitive<isize>,
+        C: Coordinate,
+        Dim<[Ix; D]>: Dimension,
+    {
+        let Some(bounds) = &mut self.cells else {
+            return;
+        };
+
+        let mut index: [C; D] = from_chunk_to_cell_index(chunk_dim, shift, key);
+        for (d, i) in index.iter_mut().enumerate() {
+            *i += C::from_ix(cell_index[d]);
+        }
+
+        if null {
+            if let Some(b) = bounds
+                && (0..D).any(|d| index[d] == b.start[d] || index[d] == b.end[d] - C::one())
+            {
+                self.cells = None;
+            }
+        } else {
+            let point = BoundingBox {
+                start: index,
+                end: index.map(|i| i.saturating_add(C::one())),
+            };
+            *bounds = Some(match bounds {
+                Some(b) => b.union(&point),
+                None => point,
+            });
+        }
+    }
+}
+
+/// Keep track of the boundaries of the gridmap
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
+where
+    A: Cell,
+    C: Coordinate,
+{
+    /// Keep track of the extents of the chunks as they are inserted and removed,
+    /// so that `boundaries` only visits the extreme chunks
+    /// and `cached_boundaries` reuses its result while no extreme chunk changes.
+    pub fn track_boundaries(&mut self, track: bool)
+    where
+        Ic: AsPrimitive<isize>,
+    {
+        self.extents = track.then(|| Extents::new(self.map.keys()));
+    }
+
+    /// Returns true if the extents of the chunks are tracked
+    #[inline]
+    pub fn is_tracking_boundaries(&self) -> bool {
+        self.extents.is_some()
+    }
+
+    /// Find the boundaries of the gridmap, the end being exclusive,
+    /// and remember them until an extreme chunk changes.
+    /// Equivalent to `boundaries` if the boundaries are not tracked.
+    pub fn cached_boundaries(&mut self) -> Option<BoundingBox<D, C>>
+    where
+        A: Clone,
+        Ic: Eq + Hash + TryFrom<isize> + AsPrimitive<isize>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        if let Some(bounds) = self.extents.as_ref().and_then(Extents::cached) {
+            return bounds;
+        }
+        let bounds = self.boundaries();
+        if let Some(extents) = &mut self.extents {
+            extents.cache(bounds);
+        }
+        bounds
+    }
+}
@@ -0,0 +1,57 @@
+//! Iterator over all the indices of a bounding box
+
+use super::BoundingBox;
+use crate::coordinate::Coordinate;
+
+/// Iterator over all the indices of a bounding box,
+/// the last dimension varying the fastest.
+pub struct Indices<const D: usize, C = isize> {
+    /// Boundaries to iterate over
+    bounds: BoundingBox<D, C>,
+
+    /// Next index to return
+    next: Option<[C; D]>,
+}
+
+impl<const D: usize, C> Indices<D, C>
+where
+    C: Coordinate,
+{
+    /// Start iterating at the first index of the bounding box
+    #[inline]
+    pub(super) fn new(bounds: &BoundingBox<D, C>) -> Self {
+        Self {
+            bounds: *bounds,
+            next: (!bounds.is_empty()).then_some(bounds.start),
+        }
+    }
+}
+
+/// Access next element of the iterator
+impl<const D: usize, C> Iterator for Indices<D, C>
+where
+    C: Coordinate,
+{
+    type Item = [C; D];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.next?;
+
+        // increment the last dimension and carry over to the previous ones
+        let mut next = index;
+        let mut d = D;
+        self.next = loop {
+            if d == 0 {
+                break None;
+            }
+            d -= 1;
+            next[d] += C::one();
+            if next[d] < self.bounds.end[d] {
+                break Some(next);
+            }
+            next[d] = self.bounds.start[d];
+        };
+
+        Some(index)
+    }
+}
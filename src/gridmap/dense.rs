@@ -0,0 +1,348 @@
+//! Conversion between regions of the GridMap and dense arrays
+
+use super::{
+    GridMap,
+    bounding_box::BoundingBox,
+    chunk_key_component,
+    iterator::{chunk_bounds, from_chunk_to_cell_index},
+    slot::Slot,
+    storage::Storage,
+    try_chunk_key,
+};
+use crate::{cell::Cell, coordinate::Coordinate};
+use core::hash::Hash;
+use hashbrown::hash_map::Entry;
+use ndarray::{Array, ArrayView, Dim, Dimension, IntoDimension, Ix, Slice, Zip};
+use num_traits::AsPrimitive;
+
+/// How the null cells of a dense array are written into the gridmap
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum WriteMode {
+    /// Null cells are ignored, the cells of the gridmap are left unchanged
+    #[default]
+    SkipNull,
+
+    /// Null cells clear the cells of the gridmap
+    ClearNull,
+}
+
+/// Copy regions of the gridmap from and to dense arrays
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
+where
+    A: Cell,
+    C: Coordinate,
+{
+    /// Copy the cells within the bounding box into a dense array,
+    /// the first cell of the array being the start of the bounding box.
+    /// Cells of missing chunks are null.
+    /// Panics if the volume of the bounding box overflows a `usize`.
+    pub fn to_dense(&self, bounds: &BoundingBox<D, C>) -> Array<A, Dim<[Ix; D]>>
+    where
+        A: Clone,
+        Ic: Eq + Hash + TryFrom<isize>,
+        [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        assert!(
+            bounds.checked_volume().is_some(),
+            "the bounding box is too large for a dense array"
+        );
+        let mut array = Array::from_elem(bounds.size(), A::NULL);
+
+        for key in bounds.chunk_range(&self.chunk_dim).indices() {
+            let Some(chunk) = try_chunk_key(&key).and_then(|key| self.map.get(&key)) else {
+                continue;
+            };
+            let origin = from_chunk_to_cell_index(&self.chunk_dim, &self.shift, &key);
+            let Some(overlap) = chunk_bounds(&self.chunk_dim, &origin).intersection(bounds) else {
+                continue;
+            };
+
+            // copy the overlapping part of the chunk
+            let mut target = array.slice_each_axis_mut(|axis| {
+                local_slice(&overlap, &bounds.start, axis.axis.index())
+            });
+            match chunk.storage() {
+                Storage::Dense(cells) => target.assign(
+                    &cells
+                        .slice_each_axis(|axis| local_slice(&overlap, &origin, axis.axis.index())),
+                ),
+                Storage::Uniform { cell, .. } => target.fill(cell.clone()),
+                storage => {
+                    for (local, cell) in target.indexed_iter_mut() {
+                        let local = local.into_dimension();
+                        let mut cell_index = [0; D];
+                        for d in 0..D {
+                            cell_index[d] = (overlap.start[d] - origin[d]).to_ix() + local[d];
+                        }
+                        *cell = storage[Dim(cell_index)].clone();
+                    }
+                }
+            }
+        }
+
+        array
+    }
+
+    /// Write the cells of a dense array into the gridmap,
+    /// the first cell of the array being written at the given origin.
+    /// The array is copied chunk by chunk, chunks left empty are removed.
+    pub fn write_dense(
+        &mut self,
+        origin: &[C; D],
+        array: ArrayView<'_, A, Dim<[Ix; D]>>,
+        mode: WriteMode,
+    ) where
+        A: PartialEq + Clone,
+        Ic: Eq + Hash + TryFrom<isize> + AsPrimitive<isize>,
+        [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        let mut bounds = BoundingBox {
+            start: *origin,
+            end: *origin,
+        };
+        for (d, &len) in array.shape().iter().enumerate() {
+            bounds.end[d] += C::from_ix(len);
+        }
+
+        for key in bounds.chunk_range(&self.chunk_dim).indices() {
+            let chunk_origin = from_chunk_to_cell_index(&self.chunk_dim, &self.shift, &key);
+            let chunk_box = chunk_bounds(&self.chunk_dim, &chunk_origin);
+            let Some(overlap) = chunk_box.intersection(&bounds) else {
+                continue;
+            };
+            let source =
+                array.slice_each_axis(|axis| local_slice(&overlap, origin, axis.axis.index()));
+            let chunk_index = key.map(chunk_key_component);
+
+            // a chunk fully covered can be replaced entirely
+            if mode == WriteMode::ClearNull && overlap == chunk_box {
+                let chunk = Slot::new(Storage::Dense(source.to_owned()));
+                if chunk.occupied() == 0 {
+                    if self.map.remove(&chunk_index).is_some()
+                        && let Some(extents) = &mut self.extents
+                    {
+                        extents.touch_chunk(&self.chunk_dim, &self.shift, &chunk_index);
+                        extents.remove_chunk(&chunk_index);
+                    }
+                } else {
+                    if let Some(extents) = &mut self.extents {
+                        if !self.map.contains_key(&chunk_index) {
+                            extents.insert_chunk(&chunk_index);
+                        }
+                        extents.touch_chunk(&self.chunk_dim, &self.shift, &chunk_index);
+                    }
+                    self.map.insert(chunk_index, chunk);
+                }
+                continue;
+            }
+
+            // null cells are skipped or have nothing to erase in a missing chunk
+            let mut entry = match self.map.entry(chunk_index) {
+                Entry::Occupied(_) if mode == WriteMode::SkipNull && is_null(&source) => {
+                    continue;
+                }
+                Entry::Occupied(entry) => entry,
+                Entry::Vacant(_) if is_null(&source) => continue,
+                Entry::Vacant(entry) => {
+                    if let Some(extents) = &mut self.extents {
+                        extents.insert_chunk(entry.key());
+                    }
+                    entry.insert_entry(Slot::empty(Storage::new(&self.chunk_dim)))
+                }
+            };
+            if let Some(extents) = &mut self.extents {
+                extents.touch_chunk(&self.chunk_dim, &self.shift, entry.key());
+            }
+
+            // copy the overlapping part of the array
+            let chunk = entry.get_mut();
+            let target = chunk.storage_mut().dense_mut().slice_each_axis_mut(|axis| {
+                local_slice(&overlap, &chunk_origin, axis.axis.index())
+            });
+            match mode {
+                WriteMode::SkipNull => Zip::from(target).and(&source).for_each(|cell, new| {
+                    if !new.is_null() {
+                        *cell = new.clone();
+                    }
+                }),
+                WriteMode::ClearNull => source.assign_to(target),
+            }
+
+            // if the chunk end up empty, remove it from the map
+            if chunk.occupied() == 0 {
+                if let Some(extents) = &mut self.extents {
+                    extents.remove_chunk(entry.key());
+                }
+                entry.remove();
+            }
+        }
+    }
+}
+
+/// Range of the bounding box along the given axis relative to the origin
+#[inline]
+fn local_slice<const D: usize, C>(bounds: &BoundingBox<D, C>, origin: &[C; D], d: usize) -> Slice
+where
+    C: Coordinate,
+{
+    Slice::from((bounds.start[d] - origin[d]).as_()..(bounds.end[d] - origin[d]).as_())
+}
+
+/// Returns true if every cell of the array is null
+#[inline]
+fn is_null<A, const D: usize>(array: &ArrayView<'_, A, Dim<[Ix; D]>>) -> bool
+where
+    A: Cell,
+    Dim<[Ix; D]>: Dimension,
+{
+    array.iter().all(Cell::is_null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WriteMode;
+    use crate::gridmap::{GridMap, bounding_box::BoundingBox};
+    use ndarray::{Array2, s};
+
+    /// Array whose cells encode their index, with a null cell every seventh cell
+    fn pattern(shape: [usize; 2]) -> Array2<u8> {
+        Array2::from_shape_fn(shape, |(x, y)| {
+            let cell = (x * shape[1] + y) as u8;
+            if cell.is_multiple_of(7) { 0 } else { cell }
+        })
+    }
+
+    #[test]
+    fn to_dense_across_negative_chunks() {
+        let mut gridmap = GridMap::<u8, 2>::new([4, 3]);
+        gridmap.set(&[-5, -4], 1);
+        gridmap.set(&[-1, 2], 2);
+        gridmap.set(&[3, -1], 3);
+        gridmap.set(&[20, 20], 4);
+        // a uniform chunk and a missing one inside of the region
+        gridmap.fill(
+            &BoundingBox {
+                start: [0, 0],
+                end: [4, 3],
+            },
+            5,
+        );
+
+        let bounds = BoundingBox {
+            start: [-5, -4],
+            end: [4, 3],
+        };
+        let array = gridmap.to_dense(&bounds);
+        assert_eq!(array.shape(), [9, 7]);
+        for ((x, y), &cell) in array.indexed_iter() {
+            let index = [x as isize - 5, y as isize - 4];
+            assert_eq!(cell, gridmap.get(&index), "{index:?}");
+        }
+        assert_eq!(array[[0, 0]], 1);
+        assert_eq!(array[[4, 6]], 2);
+        assert_eq!(array[[8, 3]], 3);
+        assert_eq!(array[[5, 4]], 5);
+    }
+
+    #[test]
+    fn round_trip_partial_chunks() {
+        let mut gridmap = GridMap::<u8, 2>::new([4, 4]);
+        let array = pattern([6, 9]);
+        gridmap.write_dense(&[-3, -5], array.view(), WriteMode::SkipNull);
+        let bounds = BoundingBox {
+            start: [-3, -5],
+            end: [3, 4],
+        };
+        assert_eq!(gridmap.to_dense(&bounds), array);
+
+        // a region covering parts of the written cells
+        let bounds = BoundingBox {
+            start: [-4, -1],
+            end: [1, 6],
+        };
+        let part = gridmap.to_dense(&bounds);
+        assert_eq!(part.slice(s![1.., ..5]), array.slice(s![..4, 4..]));
+        assert!(part.slice(s![0, ..]).iter().all(|&cell| cell == 0));
+        assert!(part.slice(s![.., 5..]).iter().all(|&cell| cell == 0));
+    }
+
+    #[test]
+    fn skip_null() {
+        let mut gridmap = GridMap::<u8, 2>::new([4, 4]);
+        gridmap.fill(
+            &BoundingBox {
+                start: [-8, -8],
+                end: [8, 8],
+            },
+            100,
+        );
+        let array = pattern([6, 9]);
+        gridmap.write_dense(&[-3, -5], array.view(), WriteMode::SkipNull);
+
+        // null cells of the array leave the gridmap unchanged
+        for ((x, y), &cell) in array.indexed_iter() {
+            let index = [x as isize - 3, y as isize - 5];
+            let expected = if cell == 0 { 100 } else { cell };
+            assert_eq!(gridmap.get(&index), expected);
+        }
+        assert_eq!(gridmap.len(), 256);
+
+        // a null array allocates nothing
+        gridmap.write_dense(&[40, 40], Array2::zeros([5, 5]).view(), WriteMode::SkipNull);
+        assert_eq!(gridmap.map.len(), 16);
+    }
+
+    #[test]
+    fn clear_null() {
+        let mut gridmap = GridMap::<u8, 2>::new([4, 4]);
+        gridmap.track_boundaries(true);
+        gridmap.fill(
+            &BoundingBox {
+                start: [-8, -8],
+                end: [8, 8],
+            },
+            100,
+        );
+        let array = pattern([6, 9]);
+        gridmap.write_dense(&[-3, -5], array.view(), WriteMode::ClearNull);
+        for ((x, y), &cell) in array.indexed_iter() {
+            assert_eq!(gridmap.get(&[x as isize - 3, y as isize - 5]), cell);
+        }
+        assert_eq!(gridmap.get(&[-4, -5]), 100);
+
+        // clearing whole chunks and parts of others frees the chunks left empty
+        let zeros = Array2::zeros([16, 16]);
+        gridmap.write_dense(&[-8, -8], zeros.view(), WriteMode::ClearNull);
+        assert_eq!(gridmap.map.len(), 0);
+        assert_eq!(gridmap.boundaries(), None);
+
+        gridmap.write_dense(&[-3, -5], array.view(), WriteMode::ClearNull);
+        gridmap.write_dense(&[-3, -5], zeros.slice(s![..6, ..9]), WriteMode::ClearNull);
+        assert_eq!(gridmap.map.len(), 0);
+    }
+
+    #[test]
+    fn unaligned_shapes() {
+        let mut gridmap = GridMap::<u8, 2>::new([4, 4]);
+
+        // an array with an empty axis writes nothing
+        gridmap.write_dense(&[0, 0], Array2::ones([0, 5]).view(), WriteMode::ClearNull);
+        assert_eq!(gridmap.map.len(), 0);
+        let empty = BoundingBox {
+            start: [2, 2],
+            end: [2, 7],
+        };
+        assert_eq!(gridmap.to_dense(&empty).shape(), [0, 5]);
+
+        // a transposed view is written along its logical axes
+        let array = pattern([3, 10]);
+        gridmap.write_dense(&[-1, -2], array.t(), WriteMode::SkipNull);
+        let bounds = BoundingBox {
+            start: [-1, -2],
+            end: [9, 1],
+        };
+        assert_eq!(gridmap.to_dense(&bounds), array.t());
+    }
+}
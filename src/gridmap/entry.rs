@@ -0,0 +1,594 @@
+//! Entry API to access a cell of the GridMap
+
+use super::{GridMap, bounding_box::extents::Extents, slot::Slot, storage::Storage};
+use crate::{cell::Cell, coordinate::Coordinate};
+use core::{
+    hash::Hash,
+    mem,
+    ops::{Deref, DerefMut, IndexMut},
+};
+use hashbrown::hash_map;
+use ndarray::{Dim, Dimension, IntoDimension, Ix};
+use num_traits::{AsPrimitive, ConstZero, ToPrimitive};
+
+/// Get an entry over a cell of the gridmap
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
+where
+    A: Cell,
+    C: Coordinate,
+{
+    /// Get the entry of the cell at the given index.
+    /// The chunk index and the cell index are computed only once.
+    pub fn entry<I>(&mut self, index: &[I; D]) -> Entry<'_, A, D, Ic, C>
+    where
+        A: PartialEq + Clone,
+        Ic: Eq + Hash + ConstZero + TryFrom<isize> + AsPrimitive<isize>,
+        I: ToPrimitive + Copy,
+        [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        let (chunk_index, cell_index) = self.split_index(index);
+        let chunk_dim = self.chunk_dim;
+        let shift = self.shift;
+        let extents = self.extents.as_mut();
+
+        match self.map.entry(chunk_index) {
+            hash_map::Entry::Occupied(entry) => {
+                if entry.get().storage()[cell_index].is_null() {
+                    Entry::Vacant(VacantEntry {
+                        chunk: VacantChunk::Existing(entry),
+                        cell_index,
+                        chunk_dim,
+                        shift,
+                        extents,
+                    })
+                } else {
+                    Entry::Occupied(OccupiedEntry {
+                        entry: Some(entry),
+                        cell_index,
+                        chunk_dim,
+                        shift,
+                        extents,
+                    })
+                }
+            }
+            hash_map::Entry::Vacant(entry) => Entry::Vacant(VacantEntry {
+                chunk: VacantChunk::Missing(entry),
+                cell_index,
+                chunk_dim,
+                shift,
+                extents,
+            }),
+        }
+    }
+}
+
+/// Entry over a cell of the gridmap
+pub enum Entry<'m, A, const D: usize, Ic = isize, C = isize>
+where
+    A: Cell + PartialEq + Clone,
+    Ic: Eq + Hash + AsPrimitive<isize>,
+    C: Coordinate,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    /// The cell is not null
+    Occupied(OccupiedEntry<'m, A, D, Ic, C>),
+
+    /// The cell is null
+    Vacant(VacantEntry<'m, A, D, Ic, C>),
+}
+
+/// Entry over a non-null cell, the chunk is freed if it ends up empty once the entry is dropped
+pub struct OccupiedEntry<'m, A, const D: usize, Ic = isize, C = isize>
+where
+    A: Cell + PartialEq + Clone,
+    Ic: Eq + Hash + AsPrimitive<isize>,
+    C: Coordinate,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    /// Entry of the chunk containing the cell, taken when the entry is consumed
+    entry: Option<hash_map::OccupiedEntry<'m, [Ic; D], Slot<A, D>>>,
+
+    /// Index of the cell inside of the chunk
+    cell_index: Dim<[Ix; D]>,
+
+    /// Dimensions of the chunks in the gridmap
+    chunk_dim: [Ix; D],
+
+    /// Base 2 logarithm of the chunk dimensions if they are all powers of two
+    shift: Option<[u32; D]>,
+
+    /// Extents of the gridmap, if they are tracked
+    extents: Option<&'m mut Extents<D, C>>,
+}
+
+/// Entry over a null cell
+pub struct VacantEntry<'m, A, const D: usize, Ic = isize, C = isize>
+where
+    A: Cell + PartialEq + Clone,
+    Ic: Eq + Hash + AsPrimitive<isize>,
+    C: Coordinate,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    /// Entry of the chunk which would contain the cell
+    chunk: VacantChunk<'m, A, D, Ic>,
+
+    /// Index of the cell inside of the chunk
+    cell_index: Dim<[Ix; D]>,
+
+    /// Dimensions of the chunks in the gridmap
+    chunk_dim: [Ix; D],
+
+    /// Base 2 logarithm of the chunk dimensions if they are all powers of two
+    shift: Option<[u32; D]>,
+
+    /// Extents of the gridmap, if they are tracked
+    extents: Option<&'m mut Extents<D, C>>,
+}
+
+/// Chunk which would contain a null cell
+enum VacantChunk<'m, A, const D: usize, Ic> {
+    /// The chunk does not exist yet
+    Missing(hash_map::VacantEntry<'m, [Ic; D], Slot<A, D>>),
+
+    /// The chunk exists but the cell is null
+    Existing(hash_map::OccupiedEntry<'m, [Ic; D], Slot<A, D>>),
+}
+
+/// Guard over a cell inserted through an entry, the cell is written back when the guard is dropped.
+/// A missing chunk is only allocated if the cell ends up not null
+/// and a chunk is freed if it ends up empty.
+pub struct EntryMut<'m, A, const D: usize, Ic = isize, C = isize>
+where
+    A: Cell + PartialEq + Clone,
+    Ic: Eq + Hash + AsPrimitive<isize>,
+    C: Coordinate,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    /// Entry of the cell, taken when the guard is dropped
+    entry: Option<Entry<'m, A, D, Ic, C>>,
+
+    /// Copy of the cell to edit
+    cell: A,
+}
+
+impl<'m, A, const D: usize, Ic, C> Entry<'m, A, D, Ic, C>
+where
+    A: Cell + PartialEq + Clone,
+    C: Coordinate,
+    Ic: Eq + Hash + AsPrimitive<isize>,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    /// Set the cell if it is null and return a guard over it
+    #[inline]
+    pub fn or_insert(self, default: A) -> EntryMut<'m, A, D, Ic, C> {
+        match self {
+            Self::Occupied(entry) => entry.into_guard(),
+            Self::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Set the cell with the result of the function if it is null
+    /// and return a guard over it
+    #[inline]
+    pub fn or_insert_with<F>(self, default: F) -> EntryMut<'m, A, D, Ic, C>
+    where
+        F: FnOnce() -> A,
+    {
+        match self {
+            Self::Occupied(entry) => entry.into_guard(),
+            Self::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Modify the cell if it is not null,
+    /// the entry becomes vacant if the cell is set to null
+    #[inline]
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut A),
+    {
+        match self {
+            Self::Occupied(mut entry) => {
+                let mut cell = entry.get().clone();
+                f(&mut cell);
+                entry.insert(cell);
+                if entry.get().is_null() {
+                    Self::Vacant(entry.into_vacant())
+                } else {
+                    Self::Occupied(entry)
+                }
+            }
+            Self::Vacant(entry) => Self::Vacant(entry),
+        }
+    }
+}
+
+impl<'m, A, const D: usize, Ic, C> OccupiedEntry<'m, A, D, Ic, C>
+where
+    A: Cell + PartialEq + Clone,
+    C: Coordinate,
+    Ic: Eq + Hash + AsPrimitive<isize>,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    /// Entry of the chunk, only taken by the methods consuming the entry
+    #[inline]
+    fn chunk(&self) -> &hash_map::OccupiedEntry<'m, [Ic; D], Slot<A, D>> {
+        self.entry
+            .as_ref()
+            .expect("the chunk entry is only taken on consumption")
+    }
+
+    /// Mutable entry of the chunk
+    #[inline]
+    fn chunk_mut(&mut self) -> &mut hash_map::OccupiedEntry<'m, [Ic; D], Slot<A, D>> {
+        self.entry
+            .as_mut()
+            .expect("the chunk entry is only taken on consumption")
+    }
+
+    /// Access the cell
+    #[inline]
+    pub fn get(&self) -> &A {
+        &self.chunk().get().storage()[self.cell_index]
+    }
+
+    /// Access the cell as mutable, the chunk is converted to a dense array
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut A {
+        let cell_index = self.cell_index;
+        if let Some(extents) = &mut self.extents {
+            let key = self
+                .entry
+                .as_ref()
+                .expect("the chunk entry is only taken on consumption")
+                .key();
+            extents.touch_chunk(&self.chunk_dim, &self.shift, key);
+        }
+        self.chunk_mut()
+            .get_mut()
+            .storage_mut()
+            .index_mut(cell_index)
+    }
+
+    /// Convert the entry into a mutable reference to the cell,
+    /// the chunk is converted to a dense array.
+    /// The chunk is kept even if the cell is set to null through the reference,
+    /// use `GridMap::prune` to free it.
+    #[inline]
+    pub fn into_mut(mut self) -> &'m mut A {
+        let entry = self
+            .entry
+            .take()
+            .expect("the chunk entry is only taken on consumption");
+        if let Some(extents) = self.extents.take() {
+            extents.touch_chunk(&self.chunk_dim, &self.shift, entry.key());
+        }
+        entry.into_mut().storage_mut().index_mut(self.cell_index)
+    }
+
+    /// Replace the cell and return the previous one
+    #[inline]
+    pub fn insert(&mut self, cell: A) -> A {
+        let previous = self.get().clone();
+        let cell_index = self.cell_index;
+        if let Some(extents) = &mut self.extents {
+            let key = self
+                .entry
+                .as_ref()
+                .expect("the chunk entry is only taken on consumption")
+                .key();
+            extents.set_cell(
+                &self.chunk_dim,
+                &self.shift,
+                key,
+                &cell_index,
+                cell.is_null(),
+            );
+        }
+        self.chunk_mut().get_mut().set(&cell_index, cell);
+        previous
+    }
+
+    /// Set the cell to null and return it, the chunk is freed if it ends up empty
+    #[inline]
+    pub fn remove(mut self) -> A {
+        self.insert(A::NULL)
+    }
+
+    /// Convert the entry into a guard over its cell
+    fn into_guard(self) -> EntryMut<'m, A, D, Ic, C> {
+        EntryMut {
+            cell: self.get().clone(),
+            entry: Some(Entry::Occupied(self)),
+        }
+    }
+
+    /// Convert the entry over a cell that was set to null into a vacant entry,
+    /// the chunk is freed if it ended up empty
+    fn into_vacant(mut self) -> VacantEntry<'m, A, D, Ic, C> {
+        let entry = self
+            .entry
+            .take()
+            .expect("the chunk entry is only taken on consumption");
+        let mut extents = self.extents.take();
+        let chunk = if entry.get().occupied() == 0 {
+            if let Some(extents) = extents.as_deref_mut() {
+                extents.remove_chunk(entry.key());
+            }
+            match entry.replace_entry_with(|_, _| None) {
+                hash_map::Entry::Vacant(entry) => VacantChunk::Missing(entry),
+                hash_map::Entry::Occupied(entry) => VacantChunk::Existing(entry),
+            }
+        } else {
+            VacantChunk::Existing(entry)
+        };
+        VacantEntry {
+            chunk,
+            cell_index: self.cell_index,
+            chunk_dim: self.chunk_dim,
+            shift: self.shift,
+            extents,
+        }
+    }
+}
+
+/// Free the chunk if it ended up empty
+impl<A, const D: usize, Ic, C> Drop for OccupiedEntry<'_, A, D, Ic, C>
+where
+    A: Cell + PartialEq + Clone,
+    C: Coordinate,
+    Ic: Eq + Hash + AsPrimitive<isize>,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    fn drop(&mut self) {
+        let Some(entry) = self.entry.take() else {
+            return;
+        };
+        // the chunk cannot be empty if the cell is still set
+        if !entry.get().storage()[self.cell_index].is_null() || entry.get().occupied() != 0 {
+            return;
+        }
+        if let Some(extents) = self.extents.as_deref_mut() {
+            extents.remove_chunk(entry.key());
+        }
+        entry.remove();
+    }
+}
+
+impl<'m, A, const D: usize, Ic, C> VacantEntry<'m, A, D, Ic, C>
+where
+    A: Cell + PartialEq + Clone,
+    C: Coordinate,
+    Ic: Eq + Hash + AsPrimitive<isize>,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    /// Set the cell and return a guard over it.
+    /// The chunk is only created once the guard is dropped, if the cell is not null.
+    #[inline]
+    pub fn insert(self, cell: A) -> EntryMut<'m, A, D, Ic, C> {
+        EntryMut {
+            entry: Some(Entry::Vacant(self)),
+            cell,
+        }
+    }
+
+    /// Write the cell, creating the chunk if necessary
+    fn write(self, cell: A) {
+        if cell.is_null() {
+            return;
+        }
+        let slot = match self.chunk {
+            VacantChunk::Missing(entry) => {
+                if let Some(extents) = self.extents {
+                    extents.insert_chunk(entry.key());
+                    extents.set_cell(
+                        &self.chunk_dim,
+                        &self.shift,
+                        entry.key(),
+                        &self.cell_index,
+                        false,
+                    );
+                }
+                entry.insert(Slot::empty(Storage::new(&self.chunk_dim)))
+            }
+            VacantChunk::Existing(entry) => {
+                if let Some(extents) = self.extents {
+                    extents.set_cell(
+                        &self.chunk_dim,
+                        &self.shift,
+                        entry.key(),
+                        &self.cell_index,
+                        false,
+                    );
+                }
+                entry.into_mut()
+            }
+        };
+        slot.set(&self.cell_index, cell);
+    }
+}
+
+/// Read the cell
+impl<A, const D: usize, Ic, C> Deref for EntryMut<'_, A, D, Ic, C>
+where
+    A: Cell + PartialEq + Clone,
+    Ic: Eq + Hash + AsPrimitive<isize>,
+    C: Coordinate,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    type Target = A;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.cell
+    }
+}
+
+/// Edit the cell
+impl<A, const D: usize, Ic, C> DerefMut for EntryMut<'_, A, D, Ic, C>
+where
+    A: Cell + PartialEq + Clone,
+    Ic: Eq + Hash + AsPrimitive<isize>,
+    C: Coordinate,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.cell
+    }
+}
+
+/// Write the cell back into the gridmap
+impl<A, const D: usize, Ic, C> Drop for EntryMut<'_, A, D, Ic, C>
+where
+    A: Cell + PartialEq + Clone,
+    Ic: Eq + Hash + AsPrimitive<isize>,
+    C: Coordinate,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    fn drop(&mut self) {
+        let cell = mem::replace(&mut self.cell, A::NULL);
+        match self.entry.take() {
+            // the occupied entry frees the chunk if it ends up empty
+            Some(Entry::Occupied(mut entry)) if *entry.get() != cell => {
+                entry.insert(cell);
+            }
+            Some(Entry::Vacant(entry)) => entry.write(cell),
+            Some(Entry::Occupied(_)) | None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Entry;
+    use crate::gridmap::{GridMap, bounding_box::BoundingBox};
+
+    #[test]
+    fn or_insert() {
+        let mut gridmap = GridMap::<u8, 2>::new([4, 4]);
+        *gridmap.entry(&[5, -5]).or_insert(2) += 1;
+        assert_eq!(gridmap.get(&[5, -5]), 3);
+        assert_eq!(gridmap.map.len(), 1);
+
+        // the cell is already set
+        *gridmap.entry(&[5, -5]).or_insert(7) += 1;
+        assert_eq!(gridmap.get(&[5, -5]), 4);
+
+        // inserting null into a missing chunk allocates nothing
+        gridmap.entry(&[-20, 20]).or_insert(0);
+        assert_eq!(gridmap.map.len(), 1);
+
+        // setting the last cell to null frees the chunk
+        *gridmap.entry(&[5, -5]).or_insert(1) = 0;
+        assert_eq!(gridmap.get(&[5, -5]), 0);
+        assert_eq!(gridmap.map.len(), 0);
+    }
+
+    #[test]
+    fn or_insert_with() {
+        let mut gridmap = GridMap::<u8, 2>::new([4, 4]);
+        gridmap.entry(&[1, 1]).or_insert_with(|| 5);
+        assert_eq!(gridmap.get(&[1, 1]), 5);
+
+        let mut called = false;
+        gridmap.entry(&[1, 1]).or_insert_with(|| {
+            called = true;
+            6
+        });
+        assert!(!called);
+        assert_eq!(gridmap.get(&[1, 1]), 5);
+
+        gridmap.entry(&[9, 9]).or_insert_with(|| 0);
+        assert_eq!(gridmap.map.len(), 1);
+    }
+
+    #[test]
+    fn and_modify() {
+        let mut gridmap = GridMap::<u8, 2>::new([4, 4]);
+        gridmap.set(&[-1, -1], 3);
+        gridmap.set(&[-2, -2], 4);
+
+        gridmap.entry(&[-1, -1]).and_modify(|cell| *cell *= 2);
+        assert_eq!(gridmap.get(&[-1, -1]), 6);
+
+        // a vacant entry is left untouched
+        gridmap.entry(&[-3, -3]).and_modify(|cell| *cell = 1);
+        assert_eq!(gridmap.get(&[-3, -3]), 0);
+
+        // the chunk is kept as long as a cell remains
+        let entry = gridmap.entry(&[-1, -1]).and_modify(|cell| *cell = 0);
+        assert!(matches!(entry, Entry::Vacant(_)));
+        drop(entry);
+        assert_eq!(gridmap.map.len(), 1);
+
+        // then freed, and the vacant entry can still insert into it
+        gridmap
+            .entry(&[-2, -2])
+            .and_modify(|cell| *cell = 0)
+            .or_insert(8);
+        assert_eq!(gridmap.get(&[-2, -2]), 8);
+        assert_eq!(gridmap.map.len(), 1);
+
+        gridmap.entry(&[-2, -2]).and_modify(|cell| *cell = 0);
+        assert_eq!(gridmap.map.len(), 0);
+    }
+
+    #[test]
+    fn remove() {
+        let mut gridmap = GridMap::<u8, 2>::new([4, 4]);
+        gridmap.set(&[0, 0], 1);
+        gridmap.set(&[1, 1], 2);
+
+        let Entry::Occupied(entry) = gridmap.entry(&[0, 0]) else {
+            panic!("the cell is set");
+        };
+        assert_eq!(entry.remove(), 1);
+        assert_eq!(gridmap.map.len(), 1);
+
+        let Entry::Occupied(mut entry) = gridmap.entry(&[1, 1]) else {
+            panic!("the cell is set");
+        };
+        assert_eq!(entry.insert(0), 2);
+        drop(entry);
+        assert_eq!(gridmap.map.len(), 0);
+        assert_eq!(gridmap.len(), 0);
+    }
+
+    #[test]
+    fn extents_stay_in_sync() {
+        let mut gridmap = GridMap::<u8, 2>::new([4, 4]);
+        gridmap.track_boundaries(true);
+        *gridmap.entry(&[-5, 7]).or_insert(1) += 1;
+        gridmap.entry(&[2, 2]).or_insert(3);
+        assert_eq!(
+            gridmap.boundaries(),
+            Some(BoundingBox {
+                start: [-5, 2],
+                end: [3, 8],
+            })
+        );
+
+        gridmap.entry(&[-5, 7]).and_modify(|cell| *cell = 0);
+        assert_eq!(gridmap.map.len(), 1);
+        assert_eq!(
+            gridmap.boundaries(),
+            Some(BoundingBox {
+                start: [2, 2],
+                end: [3, 3],
+            })
+        );
+    }
+}
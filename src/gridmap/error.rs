@@ -0,0 +1,40 @@
+//! Errors raised when accessing the cells of the GridMap
+
+use core::fmt;
+
+/// Error raised by the fallible accessors of the GridMap
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GridMapError {
+    /// A component of the index does not fit in the cell coordinate type
+    CoordinateOverflow { axis: usize },
+
+    /// A chunk coordinate does not fit in the chunk key type
+    ChunkKeyOutOfRange { axis: usize },
+
+    /// The index lies outside of the limits of the gridmap
+    OutOfBounds,
+}
+
+/// Describe the error
+impl fmt::Display for GridMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CoordinateOverflow { axis } => {
+                write!(
+                    f,
+                    "index along axis {axis} overflows the cell coordinate type"
+                )
+            }
+            Self::ChunkKeyOutOfRange { axis } => {
+                write!(
+                    f,
+                    "chunk coordinate along axis {axis} is out of range of the chunk key type"
+                )
+            }
+            Self::OutOfBounds => write!(f, "index is outside of the limits of the gridmap"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for GridMapError {}
@@ -0,0 +1,173 @@
+//! Guard to mutate a cell without allocating chunks needlessly
+
+use super::GridMap;
+use crate::{cell::Cell, coordinate::Coordinate};
+use core::{
+    hash::Hash,
+    mem,
+    ops::{Deref, DerefMut},
+};
+use ndarray::{Dim, Dimension, IntoDimension, Ix};
+use num_traits::{AsPrimitive, ConstZero, ToPrimitive};
+
+/// Get a guard over a cell of the gridmap
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
+where
+    A: Cell + PartialEq + Clone,
+    Ic: Eq + Hash + ConstZero + AsPrimitive<isize>,
+    C: Coordinate,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    /// Mutably access a cell through a guard.
+    /// The chunk is only allocated if a non-null cell is stored
+    /// and it is freed if it ends up empty once the guard is dropped.
+    pub fn cell_mut<I>(&mut self, index: &[I; D]) -> CellMut<'_, A, D, Ic, C>
+    where
+        Ic: TryFrom<isize>,
+        I: ToPrimitive + Copy,
+    {
+        let (chunk_index, cell_index) = self.split_index(index);
+        let cell = self.index_chunk_cell(&chunk_index, &cell_index).clone();
+        CellMut {
+            gridmap: self,
+            chunk_index,
+            cell_index,
+            cell,
+        }
+    }
+}
+
+/// Guard over a cell of the gridmap, the cell is written back when the guard is dropped
+pub struct CellMut<'m, A, const D: usize, Ic = isize, C = isize>
+where
+    A: Cell + PartialEq + Clone,
+    Ic: Eq + Hash + ConstZero + AsPrimitive<isize>,
+    C: Coordinate,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    /// GridMap containing the cell
+    gridmap: &'m mut GridMap<A, D, Ic, C>,
+
+    /// Index of the chunk containing the cell
+    chunk_index: [Ic; D],
+
+    /// Index of the cell inside of the chunk
+    cell_index: Dim<[Ix; D]>,
+
+    /// Copy of the cell to edit
+    cell: A,
+}
+
+/// Read the cell
+impl<A, const D: usize, Ic, C> Deref for CellMut<'_, A, D, Ic, C>
+where
+    A: Cell + PartialEq + Clone,
+    Ic: Eq + Hash + ConstZero + AsPrimitive<isize>,
+    C: Coordinate,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    type Target = A;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.cell
+    }
+}
+
+/// Edit the cell
+impl<A, const D: usize, Ic, C> DerefMut for CellMut<'_, A, D, Ic, C>
+where
+    A: Cell + PartialEq + Clone,
+    Ic: Eq + Hash + ConstZero + AsPrimitive<isize>,
+    C: Coordinate,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.cell
+    }
+}
+
+/// Write the cell back into the gridmap
+impl<A, const D: usize, Ic, C> Drop for CellMut<'_, A, D, Ic, C>
+where
+    A: Cell + PartialEq + Clone,
+    Ic: Eq + Hash + ConstZero + AsPrimitive<isize>,
+    C: Coordinate,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    fn drop(&mut self) {
+        // nothing to do if the cell was left unchanged
+        if *self
+            .gridmap
+            .index_chunk_cell(&self.chunk_index, &self.cell_index)
+            == self.cell
+        {
+            return;
+        }
+
+        let chunk_index = mem::replace(&mut self.chunk_index, [Ic::ZERO; D]);
+        let cell = mem::replace(&mut self.cell, A::NULL);
+        self.gridmap
+            .set_chunk_cell(chunk_index, &self.cell_index, cell);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gridmap::{GridMap, bounding_box::BoundingBox};
+
+    #[test]
+    fn read_allocates_nothing() {
+        let mut gridmap = GridMap::<u8, 2>::new([4, 4]);
+        assert_eq!(*gridmap.cell_mut(&[5, -5]), 0);
+        assert_eq!(gridmap.map.len(), 0);
+
+        // writing null into a missing chunk allocates nothing either
+        *gridmap.cell_mut(&[5, -5]) = 0;
+        assert_eq!(gridmap.map.len(), 0);
+    }
+
+    #[test]
+    fn write_and_free() {
+        let mut gridmap = GridMap::<u8, 2>::new([4, 4]);
+        *gridmap.cell_mut(&[5, -5]) += 3;
+        assert_eq!(gridmap.get(&[5, -5]), 3);
+        assert_eq!(gridmap.map.len(), 1);
+        assert_eq!(gridmap.len(), 1);
+
+        *gridmap.cell_mut(&[5, -5]) = 0;
+        assert_eq!(gridmap.map.len(), 0);
+        assert_eq!(gridmap.len(), 0);
+    }
+
+    #[test]
+    fn extents_stay_in_sync() {
+        let mut gridmap = GridMap::<u8, 2>::new([4, 4]);
+        gridmap.track_boundaries(true);
+        gridmap.set(&[0, 0], 1);
+        *gridmap.cell_mut(&[9, -3]) = 2;
+        assert_eq!(
+            gridmap.cached_boundaries(),
+            Some(BoundingBox {
+                start: [0, -3],
+                end: [10, 1],
+            })
+        );
+
+        *gridmap.cell_mut(&[9, -3]) = 0;
+        assert_eq!(gridmap.len(), 1);
+        assert_eq!(
+            gridmap.cached_boundaries(),
+            Some(BoundingBox {
+                start: [0, 0],
+                end: [1, 1],
+            })
+        );
+    }
+}
@@ -0,0 +1,658 @@
+//! Undo and redo the changes made to a GridMap
+
+use super::{
+    GridMap,
+    bounding_box::BoundingBox,
+    dense::WriteMode,
+    entry::Entry,
+    guard::CellMut,
+    iterator::{
+        bounded, chunk_bounds, chunks, from_chunk_to_cell_index, indexed, occupied, region, simple,
+    },
+    patch::{Patch, chunk_change},
+    slot::Slot,
+    storage::Storage,
+    try_chunk_key,
+};
+use crate::{cell::Cell, coordinate::Coordinate, transform::Transform};
+use alloc::{collections::VecDeque, vec::Vec};
+use core::{
+    hash::Hash,
+    ops::{Deref, Index, IndexMut},
+};
+use hashbrown::HashMap;
+use ndarray::{ArrayView, Dim, Dimension, IntoDimension, Ix};
+use num_traits::{AsPrimitive, ConstZero, ToPrimitive};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// GridMap recording the changes made within transactions so that they can be undone.
+/// Changes made outside of a transaction cannot be undone and clear the history.
+/// The most recent transaction is always kept, even if it alone exceeds the budget.
+/// The gridmap can only be modified through the methods of the history,
+/// use `into_inner` to access the other methods of the gridmap.
+pub struct History<A, const D: usize, Ic = isize, C = isize>
+where
+    A: Cell,
+{
+    /// GridMap being edited
+    gridmap: GridMap<A, D, Ic, C>,
+
+    /// Former cells of the chunks touched by the open transaction,
+    /// `None` if no transaction is open
+    touched: Option<HashMap<[Ic; D], Option<Slot<A, D>>>>,
+
+    /// Transactions which can be undone, the oldest first
+    undo: VecDeque<Patch<A, D, Ic>>,
+
+    /// Transactions which can be redone, the most recently undone last
+    redo: Vec<Patch<A, D, Ic>>,
+
+    /// Number of cells the recorded transactions may hold
+    budget: usize,
+
+    /// Number of cells held by the recorded transactions
+    used: usize,
+}
+
+impl<A, const D: usize, Ic, C> History<A, D, Ic, C>
+where
+    A: Cell,
+{
+    /// Record the changes made to the gridmap,
+    /// the oldest transactions are dropped once they hold more cells than the budget.
+    pub fn new(gridmap: GridMap<A, D, Ic, C>, budget: usize) -> Self {
+        Self {
+            gridmap,
+            touched: None,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            budget,
+            used: 0,
+        }
+    }
+
+    /// Access the gridmap
+    #[inline]
+    pub fn gridmap(&self) -> &GridMap<A, D, Ic, C> {
+        &self.gridmap
+    }
+
+    /// Take the gridmap, dropping the history
+    #[inline]
+    pub fn into_inner(self) -> GridMap<A, D, Ic, C> {
+        self.gridmap
+    }
+
+    /// Returns true if a transaction is open
+    #[inline]
+    pub fn in_transaction(&self) -> bool {
+        self.touched.is_some()
+    }
+
+    /// Returns true if a transaction can be undone
+    #[inline]
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Returns true if an undone transaction can be redone
+    #[inline]
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Number of cells the recorded transactions may hold
+    #[inline]
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Number of cells held by the recorded transactions
+    #[inline]
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    /// Forget every recorded transaction, the open transaction is kept
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.used = 0;
+    }
+}
+
+/// Record, undo and redo transactions
+impl<A, const D: usize, Ic, C> History<A, D, Ic, C>
+where
+    A: Cell + PartialEq + Clone,
+    Ic: Eq + Hash + ConstZero + TryFrom<isize> + AsPrimitive<isize>,
+    C: Coordinate,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    /// Open a transaction, the changes made until `commit` are undone together.
+    /// Does nothing if a transaction is already open.
+    pub fn begin(&mut self) {
+        self.touched.get_or_insert_with(HashMap::new);
+    }
+
+    /// Close the transaction and record its changes.
+    /// The transactions which were undone can no longer be redone.
+    pub fn commit(&mut self) {
+        let Some(touched) = self.touched.take() else {
+            return;
+        };
+
+        // compare the chunks before and after the transaction
+        let changes: Vec<_> = touched
+            .iter()
+            .filter_map(|(key, old)| {
+                Some((*key, chunk_change(old.as_ref(), self.gridmap.map.get(key))?))
+            })
+            .collect();
+        if changes.is_empty() {
+            return;
+        }
+
+        let patch = Patch::new(self.gridmap.chunk_dim, changes);
+        self.used -= self.redo.drain(..).map(|patch| patch.size()).sum::<usize>();
+        self.used += patch.size();
+        self.undo.push_back(patch);
+        self.trim();
+    }
+
+    /// Revert the last transaction, committing the open one first.
+    /// Returns false if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        self.commit();
+        let Some(patch) = self.undo.pop_back() else {
+            return false;
+        };
+        self.gridmap.apply(&patch.invert());
+        self.redo.push(patch);
+        true
+    }
+
+    /// Apply again the last undone transaction, committing the open one first.
+    /// Returns false if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        self.commit();
+        let Some(patch) = self.redo.pop() else {
+            return false;
+        };
+        self.gridmap.apply(&patch);
+        self.undo.push_back(patch);
+        true
+    }
+
+    /// Change the number of cells the recorded transactions may hold,
+    /// dropping the oldest transactions if necessary.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.trim();
+    }
+
+    /// Drop the oldest transactions until the budget is respected,
+    /// the undone transactions being the last ones to go.
+    /// The most recent transaction is kept so that it can always be undone.
+    fn trim(&mut self) {
+        while self.used > self.budget && self.undo.len() > 1 {
+            if let Some(patch) = self.undo.pop_front() {
+                self.used -= patch.size();
+            }
+        }
+        while self.used > self.budget && !self.redo.is_empty() {
+            self.used -= self.redo.remove(0).size();
+        }
+    }
+
+    /// Remember the cells of a chunk before it is modified
+    fn touch(&mut self, key: &[Ic; D]) {
+        match &mut self.touched {
+            Some(touched) => {
+                touched
+                    .entry(*key)
+                    .or_insert_with(|| self.gridmap.map.get(key).cloned());
+            }
+            None => self.clear(),
+        }
+    }
+
+    /// Remember the cells of all the chunks before they are modified
+    fn touch_all(&mut self) {
+        let keys: Vec<_> = self.gridmap.map.keys().copied().collect();
+        keys.iter().for_each(|key| self.touch(key));
+    }
+
+    /// Remember the cells of the existing chunks overlapping the bounding box
+    fn touch_overlapping(&mut self, bounds: &BoundingBox<D, C>) {
+        let chunk_dim = self.gridmap.chunk_dim;
+        let shift = self.gridmap.shift;
+        let keys: Vec<_> = self
+            .gridmap
+            .map
+            .keys()
+            .filter(|key| {
+                let origin = from_chunk_to_cell_index(&chunk_dim, &shift, key);
+                chunk_bounds(&chunk_dim, &origin).overlaps_with(bounds)
+            })
+            .copied()
+            .collect();
+        keys.iter().for_each(|key| self.touch(key));
+    }
+
+    /// Remember the cells of every chunk overlapping the bounding box, missing ones included
+    fn touch_region(&mut self, bounds: &BoundingBox<D, C>) {
+        for key in bounds.chunk_range(&self.gridmap.chunk_dim).indices() {
+            if let Some(key) = try_chunk_key(&key) {
+                self.touch(&key);
+            }
+        }
+    }
+}
+
+/// Modify the gridmap while recording the changes
+impl<A, const D: usize, Ic, C> History<A, D, Ic, C>
+where
+    A: Cell + PartialEq + Clone,
+    Ic: Eq + Hash + ConstZero + TryFrom<isize> + AsPrimitive<isize>,
+    C: Coordinate,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    /// Set a cell in the gridmap
+    pub fn set<I>(&mut self, index: &[I; D], cell: A)
+    where
+        I: ToPrimitive + Copy,
+    {
+        let (chunk_index, cell_index) = self.gridmap.split_index(index);
+        self.touch(&chunk_index);
+        self.gridmap.set_chunk_cell(chunk_index, &cell_index, cell);
+    }
+
+    /// Create a mutable iterator over the non-empty cells of the gridmap
+    pub fn iter_mut(&mut self) -> occupied::IterMut<'_, A, D, Ic> {
+        self.touch_all();
+        self.gridmap.iter_mut()
+    }
+
+    /// Create a mutable iterator over the non-empty cells of the gridmap with their index
+    pub fn indexed_iter_mut(&mut self) -> indexed::IterMut<'_, A, D, Ic, C> {
+        self.touch_all();
+        self.gridmap.indexed_iter_mut()
+    }
+
+    /// Create a mutable iterator over all the cells of the chunks of the gridmap
+    pub fn raw_iter_mut(&mut self) -> simple::IterMut<'_, A, D, Ic> {
+        self.touch_all();
+        self.gridmap.raw_iter_mut()
+    }
+
+    /// Create a mutable iterator over the non-empty cells within the boundaries
+    pub fn bounded_iter_mut(
+        &mut self,
+        bounds: BoundingBox<D, C>,
+    ) -> bounded::IterMut<'_, A, D, Ic, C> {
+        self.touch_overlapping(&bounds);
+        self.gridmap.bounded_iter_mut(bounds)
+    }
+
+    /// Create a mutable iterator over every cell within the boundaries, null cells included
+    pub fn region_iter_mut(
+        &mut self,
+        bounds: BoundingBox<D, C>,
+    ) -> region::IterMut<'_, A, D, Ic, C> {
+        self.touch_region(&bounds);
+        self.gridmap.region_iter_mut(bounds)
+    }
+
+    /// Create a mutable iterator over the chunks of the gridmap
+    pub fn chunks_mut(&mut self) -> chunks::IterMut<'_, A, D, Ic, C> {
+        self.touch_all();
+        self.gridmap.chunks_mut()
+    }
+
+    /// Access a chunk of the gridmap as mutable
+    pub fn get_chunk_mut(&mut self, chunk_index: &[Ic; D]) -> Option<&mut Storage<A, D>> {
+        self.touch(chunk_index);
+        self.gridmap.get_chunk_mut::<Ic>(chunk_index)
+    }
+
+    /// Mutably access a cell of the gridmap through a guard
+    pub fn cell_mut<I>(&mut self, index: &[I; D]) -> CellMut<'_, A, D, Ic, C>
+    where
+        I: ToPrimitive + Copy,
+    {
+        let (chunk_index, _) = self.gridmap.split_index(index);
+        self.touch(&chunk_index);
+        self.gridmap.cell_mut(index)
+    }
+
+    /// Get the entry of a cell of the gridmap
+    pub fn entry<I>(&mut self, index: &[I; D]) -> Entry<'_, A, D, Ic, C>
+    where
+        I: ToPrimitive + Copy,
+    {
+        let (chunk_index, _) = self.gridmap.split_index(index);
+        self.touch(&chunk_index);
+        self.gridmap.entry(index)
+    }
+
+    /// Set every cell of the gridmap within the bounding box to the given cell
+    pub fn fill(&mut self, bounds: &BoundingBox<D, C>, cell: A) {
+        self.touch_region(bounds);
+        self.gridmap.fill(bounds, cell);
+    }
+
+    /// Write the cells of a dense array into the gridmap,
+    /// the first cell of the array being written at the given origin
+    pub fn write_dense(
+        &mut self,
+        origin: &[C; D],
+        array: ArrayView<'_, A, Dim<[Ix; D]>>,
+        mode: WriteMode,
+    ) {
+        let mut bounds = BoundingBox {
+            start: *origin,
+            end: *origin,
+        };
+        for (d, &len) in array.shape().iter().enumerate() {
+            bounds.end[d] += C::from_ix(len);
+        }
+        self.touch_region(&bounds);
+        self.gridmap.write_dense(origin, array, mode);
+    }
+
+    /// Store each chunk in the representation using the least memory,
+    /// the cells are left unchanged
+    #[inline]
+    pub fn compress(&mut self) {
+        self.gridmap.compress();
+    }
+
+    /// Free the chunks holding only null cells, the cells are left unchanged
+    #[inline]
+    pub fn prune(&mut self) {
+        self.gridmap.prune();
+    }
+
+    /// Copy the cells of the source gridmap with the given transformation,
+    /// like `GridMap::copy_to` with this gridmap as the target
+    pub fn copy_from(
+        &mut self,
+        source: &GridMap<A, D, Ic, C>,
+        transforms: &[&dyn Transform<D, C>],
+    ) {
+        for (index, cell) in source.indexed_iter_in(source.sorted_order()) {
+            let index = transforms.transform(&index);
+            self.set(&index, cell.clone());
+        }
+    }
+
+    /// Copy the cells of the source gridmap within the bounding box with the given transformation,
+    /// like `GridMap::copy_to_within` with this gridmap as the target
+    pub fn copy_from_within(
+        &mut self,
+        source: &GridMap<A, D, Ic, C>,
+        transforms: &[&dyn Transform<D, C>],
+        bounding_box: &BoundingBox<D, C>,
+    ) {
+        for (index, cell) in source.bounded_iter_in(*bounding_box, source.sorted_order()) {
+            let index = transforms.transform(&index);
+            self.set(&index, cell.clone());
+        }
+    }
+}
+
+/// Modify the gridmap in parallel while recording the changes
+#[cfg(feature = "rayon")]
+impl<A, const D: usize, Ic, C> History<A, D, Ic, C>
+where
+    A: Cell + PartialEq + Clone + Send + Sync,
+    Ic: Eq + Hash + ConstZero + TryFrom<isize> + AsPrimitive<isize> + Sync,
+    C: Coordinate,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    /// Create a mutable parallel iterator over the non-empty cells of the gridmap
+    pub fn par_iter_mut(&mut self) -> impl ParallelIterator<Item = &mut A> {
+        self.touch_all();
+        self.gridmap.par_iter_mut()
+    }
+
+    /// Create a mutable parallel iterator over the non-empty cells of the gridmap with their index
+    pub fn par_indexed_iter_mut(&mut self) -> impl ParallelIterator<Item = ([C; D], &mut A)> {
+        self.touch_all();
+        self.gridmap.par_indexed_iter_mut()
+    }
+
+    /// Create a mutable parallel iterator over the non-empty cells within the boundaries
+    pub fn par_bounded_iter_mut(
+        &mut self,
+        bounds: BoundingBox<D, C>,
+    ) -> impl ParallelIterator<Item = ([C; D], &mut A)> {
+        self.touch_overlapping(&bounds);
+        self.gridmap.par_bounded_iter_mut(bounds)
+    }
+}
+
+/// Read the gridmap
+impl<A, const D: usize, Ic, C> Deref for History<A, D, Ic, C>
+where
+    A: Cell,
+{
+    type Target = GridMap<A, D, Ic, C>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.gridmap
+    }
+}
+
+/// Indexing to access cells in the gridmap
+impl<A, const D: usize, Ic, C, I> Index<[I; D]> for History<A, D, Ic, C>
+where
+    A: Cell,
+    Ic: Eq + Hash + ConstZero + TryFrom<isize>,
+    C: Coordinate,
+    I: ToPrimitive + Copy,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    type Output = A;
+
+    /// Get a reference to the cell at the given index
+    #[inline]
+    fn index(&self, index: [I; D]) -> &Self::Output {
+        &self.gridmap[index]
+    }
+}
+
+/// Indexing to mutable access cells in the gridmap while recording the changes
+impl<A, const D: usize, Ic, C, I> IndexMut<[I; D]> for History<A, D, Ic, C>
+where
+    A: Cell + PartialEq + Default + Clone,
+    Ic: Eq + Hash + ConstZero + TryFrom<isize> + AsPrimitive<isize>,
+    C: Coordinate,
+    I: ToPrimitive + Copy,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    /// Get a mutable reference to the cell at the given index
+    fn index_mut(&mut self, index: [I; D]) -> &mut Self::Output {
+        let (chunk_index, cell_index) = self.gridmap.split_index(&index);
+        self.touch(&chunk_index);
+        self.gridmap.index_chunk_cell_mut(chunk_index, &cell_index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_redo() {
+        let mut history = History::new(GridMap::<u8, 2>::new([4, 4]), 100);
+        history.begin();
+        history.set(&[1, 2], 3);
+        history.set(&[-7, 5], 9);
+        history.commit();
+        history.begin();
+        history.set(&[1, 2], 4);
+        history[[0, 0]] = 1;
+        history.commit();
+        // added chunks count their cells, changed cells count twice
+        assert_eq!(history.used(), 2 + 2 * 2);
+
+        assert!(history.undo());
+        assert_eq!(history.get(&[1, 2]), 3);
+        assert_eq!(history.get(&[0, 0]), 0);
+        assert!(history.undo());
+        assert!(history.is_empty());
+        assert!(!history.undo());
+
+        assert!(history.redo());
+        assert!(history.redo());
+        assert!(!history.redo());
+        assert_eq!(history.get(&[1, 2]), 4);
+        assert_eq!(history.get(&[-7, 5]), 9);
+        assert_eq!(history.get(&[0, 0]), 1);
+    }
+
+    #[test]
+    fn commit_clears_redo() {
+        let mut history = History::new(GridMap::<u8, 2>::new([4, 4]), 100);
+        history.begin();
+        history.set(&[1, 2], 3);
+        history.commit();
+        history.undo();
+        assert!(history.can_redo());
+
+        history.begin();
+        history.set(&[5, 5], 1);
+        history.commit();
+        assert!(!history.can_redo());
+        assert_eq!(history.used(), 1);
+
+        // empty transactions are not recorded
+        history.begin();
+        history.set(&[6, 6], 0);
+        history.commit();
+        assert!(history.undo());
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn changes_outside_transactions() {
+        let mut history = History::new(GridMap::<u8, 2>::new([4, 4]), 100);
+        history.begin();
+        history.set(&[1, 2], 3);
+        history.commit();
+        history.set(&[1, 2], 4);
+        assert!(!history.can_undo());
+        assert_eq!(history.used(), 0);
+    }
+
+    #[test]
+    fn budget() {
+        let mut history = History::new(GridMap::<u8, 2>::new([4, 4]), 10);
+        for x in 0..5 {
+            history.begin();
+            history.set(&[x * 4, 0], 1);
+            history.commit();
+        }
+        assert_eq!(history.used(), 5);
+
+        // creating a chunk only charges its non-null cells
+        history.begin();
+        history.set(&[-100, 0], 1);
+        history.commit();
+        assert_eq!(history.used(), 6);
+        for _ in 0..6 {
+            assert!(history.undo());
+        }
+        assert!(history.is_empty());
+
+        // the oldest transactions are dropped first
+        history.set_budget(3);
+        assert_eq!(history.used(), 3);
+        assert!(history.can_redo());
+    }
+
+    #[test]
+    fn recorded_mutators() {
+        let mut history = History::new(GridMap::<u8, 2>::new([4, 4]), 1000);
+        let bounds = BoundingBox {
+            start: [-3, -3],
+            end: [6, 6],
+        };
+        history.begin();
+        history.fill(&bounds, 2);
+        history.commit();
+        let filled = history.gridmap().clone();
+
+        history.begin();
+        *history.entry(&[0, 0]).or_insert(1) += 1;
+        *history.cell_mut(&[20, 20]) = 4;
+        history.raw_iter_mut().for_each(|cell| *cell += 1);
+        let array = ndarray::Array2::from_elem([2, 9], 7);
+        history.write_dense(&[-10, 0], array.view(), WriteMode::ClearNull);
+        history
+            .chunks_mut()
+            .for_each(|mut chunk| chunk.view_mut().fill(0));
+        history.commit();
+        assert!(history.is_empty());
+
+        assert!(history.undo());
+        assert_eq!(history.gridmap(), &filled);
+        assert!(history.undo());
+        assert!(history.is_empty());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn recorded_parallel_mutators() {
+        let mut history = History::new(GridMap::<u8, 2>::new([4, 4]), 1000);
+        history.set(&[1, 1], 1);
+        history.set(&[9, 9], 1);
+        let original = history.gridmap().clone();
+
+        history.begin();
+        history.par_iter_mut().for_each(|cell| *cell = 2);
+        history
+            .par_indexed_iter_mut()
+            .for_each(|(_, cell)| *cell += 1);
+        let bounds = BoundingBox {
+            start: [0, 0],
+            end: [4, 4],
+        };
+        history
+            .par_bounded_iter_mut(bounds)
+            .for_each(|(_, cell)| *cell = 0);
+        history.commit();
+        assert_eq!(history.get(&[9, 9]), 3);
+
+        assert!(history.undo());
+        assert_eq!(history.gridmap(), &original);
+    }
+
+    #[test]
+    fn last_transaction_is_kept() {
+        let mut history = History::new(GridMap::<u8, 2>::new([4, 4]), 2);
+        history.begin();
+        history.set(&[0, 0], 1);
+        history.commit();
+        history.begin();
+        for x in 0..4 {
+            history.set(&[x, 1], 1);
+        }
+        history.commit();
+        assert_eq!(history.used(), 8);
+
+        assert!(history.undo());
+        assert!(!history.can_undo());
+        assert_eq!(history.len(), 1);
+    }
+}
@@ -1,26 +1,32 @@
 //! Indexing to access cells in the GridMap
 
 use super::GridMap;
-use crate::{cell::Cell, gridmap::make_chunk};
+use crate::{
+    cell::Cell,
+    coordinate::Coordinate,
+    gridmap::{error::GridMapError, make_chunk, slot::Slot, storage::Storage},
+};
 use core::{
     hash::Hash,
     ops::{Index, IndexMut},
 };
 use ndarray::{Dim, Dimension, IntoDimension, Ix};
-use num_traits::{AsPrimitive, ConstZero, Euclid};
+use num_traits::{AsPrimitive, ConstZero, NumCast, ToPrimitive};
 
 /// Indexing to access cells in the GridMap
-impl<A, const D: usize, Ic, I> Index<[I; D]> for GridMap<A, D, Ic>
+impl<A, const D: usize, Ic, C, I> Index<[I; D]> for GridMap<A, D, Ic, C>
 where
     A: Cell,
-    Ic: Eq + Hash + ConstZero + From<isize>,
-    I: AsPrimitive<isize>,
+    Ic: Eq + Hash + ConstZero + TryFrom<isize>,
+    C: Coordinate,
+    I: ToPrimitive + Copy,
     [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
     Dim<[Ix; D]>: Dimension,
 {
     type Output = A;
 
-    /// Get a reference to the cell at the given index
+    /// Get a reference to the cell at the given index.
+    /// Panics if the index overflows the coordinate types or lies outside of the limits.
     fn index(&self, index: [I; D]) -> &Self::Output {
         let (chunk_index, cell_index) = self.split_index(&index);
         self.index_chunk_cell(&chunk_index, &cell_index)
@@ -28,15 +34,19 @@ where
 }
 
 /// Indexing to mutable access cells in the GridMap
-impl<A, const D: usize, Ic, I> IndexMut<[I; D]> for GridMap<A, D, Ic>
+impl<A, const D: usize, Ic, C, I> IndexMut<[I; D]> for GridMap<A, D, Ic, C>
 where
-    A: Cell + Default,
-    Ic: Eq + Hash + ConstZero + From<isize>,
-    I: AsPrimitive<isize>,
+    A: Cell + Default + Clone,
+    Ic: Eq + Hash + ConstZero + TryFrom<isize> + AsPrimitive<isize>,
+    C: Coordinate,
+    I: ToPrimitive + Copy,
     [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
     Dim<[Ix; D]>: Dimension,
 {
-    /// Get a mutable reference to the cell at the given index
+    /// Get a mutable reference to the cell at the given index.
+    /// The chunk is allocated as a dense array even if the cell is only read,
+    /// use `set` or `cell_mut` to keep compressed chunks compact.
+    /// Panics if the index overflows the coordinate types or lies outside of the limits.
     fn index_mut(&mut self, index: [I; D]) -> &mut Self::Output {
         let (chunk_index, cell_index) = self.split_index(&index);
         self.index_chunk_cell_mut(chunk_index, &cell_index)
@@ -44,7 +54,7 @@ where
 }
 
 /// Index a cell knowing chunk index and cell index
-impl<A, const D: usize, Ic> GridMap<A, D, Ic>
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
 where
     A: Cell,
 {
@@ -55,7 +65,7 @@ where
         Dim<[Ix; D]>: Dimension,
     {
         if let Some(chunk) = self.map.get(chunk_index) {
-            chunk.index(*cell_index)
+            chunk.storage().index(*cell_index)
         } else {
             &self.empty
         }
@@ -63,56 +73,231 @@ where
 }
 
 /// Index a cell knowing chunk index and cell index
-impl<A, const D: usize, Ic> GridMap<A, D, Ic>
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
 where
     A: Cell,
 {
-    /// Index a cell knowing chunk index and cell index
+    /// Mutably index a cell knowing chunk index and cell index,
+    /// the chunk is converted to a dense array
     pub fn index_chunk_cell_mut(
         &mut self,
         chunk_index: [Ic; D],
         cell_index: &Dim<[Ix; D]>,
     ) -> &mut A
     where
-        A: Default,
-        Ic: Eq + Hash,
+        A: Default + Clone,
+        Ic: Eq + Hash + AsPrimitive<isize>,
+        C: Coordinate,
         [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
         Dim<[Ix; D]>: Dimension,
     {
+        if let Some(extents) = &mut self.extents {
+            if !self.map.contains_key(&chunk_index) {
+                extents.insert_chunk(&chunk_index);
+            }
+            extents.touch_chunk(&self.chunk_dim, &self.shift, &chunk_index);
+        }
         let chunk = self
             .map
             .entry(chunk_index)
-            .or_insert_with(|| make_chunk::<A, D>(&self.chunk_dim));
-        chunk.index_mut(*cell_index)
+            .or_insert_with(|| Slot::empty(Storage::Dense(make_chunk::<A, D>(&self.chunk_dim))));
+        chunk.storage_mut().index_mut(*cell_index)
+    }
+
+    /// Get a mutable reference to the cell at the given index,
+    /// fails if the index overflows the coordinate types or lies outside of the limits.
+    /// The chunk is allocated as a dense array even if the cell is only read.
+    pub fn try_index_mut<I>(&mut self, index: &[I; D]) -> Result<&mut A, GridMapError>
+    where
+        A: Default + Clone,
+        Ic: Eq + Hash + ConstZero + TryFrom<isize> + AsPrimitive<isize>,
+        C: Coordinate,
+        I: ToPrimitive + Copy,
+        [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        let (chunk_index, cell_index) = self.try_split_index(index)?;
+        Ok(self.index_chunk_cell_mut(chunk_index, &cell_index))
     }
 }
 
 /// Index a cell knowing chunk index and cell index
-impl<A, const D: usize, Ic> GridMap<A, D, Ic>
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
 where
     A: Cell,
 {
-    /// Split the index into chunk index and cell index
+    /// Split the index into chunk index and cell index.
+    /// Panics if the index overflows the coordinate types or lies outside of the limits.
     #[inline]
     pub fn split_index<I>(&self, index: &[I; D]) -> ([Ic; D], Dim<[Ix; D]>)
     where
-        Ic: ConstZero + From<isize>,
-        I: AsPrimitive<isize>,
+        Ic: ConstZero + TryFrom<isize>,
+        C: Coordinate,
+        I: ToPrimitive + Copy,
         [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
     {
+        self.try_split_index(index)
+            .unwrap_or_else(|err| panic!("invalid cell index: {err}"))
+    }
+
+    /// Split the index into chunk index and cell index,
+    /// fails if the index overflows the coordinate types or lies outside of the limits.
+    pub fn try_split_index<I>(
+        &self,
+        index: &[I; D],
+    ) -> Result<([Ic; D], Dim<[Ix; D]>), GridMapError>
+    where
+        Ic: ConstZero + TryFrom<isize>,
+        C: Coordinate,
+        I: ToPrimitive + Copy,
+        [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    {
+        // convert the index to cell coordinates
+        let mut cell = [C::zero(); D];
+        for d in 0..D {
+            cell[d] = <C as NumCast>::from(index[d])
+                .ok_or(GridMapError::CoordinateOverflow { axis: d })?;
+        }
+        if let Some(limits) = &self.limits
+            && !limits.contains(&cell)
+        {
+            return Err(GridMapError::OutOfBounds);
+        }
+
         // prepare arrays to store the results
         let mut chunk_index = [Ic::ZERO; D];
         let mut cell_index = [Ix::ZERO; D];
 
         // for each component
-        for i in 0..D {
-            let idx = index[i].as_();
-            let dim = self.chunk_dim[i] as isize;
+        for d in 0..D {
+            let (ch, cl) = match &self.shift {
+                // the arithmetic shift rounds toward negative infinity like the euclidean division
+                Some(shift) => (
+                    cell[d] >> shift[d] as usize,
+                    cell[d] & C::from_ix(self.chunk_dim[d] - 1),
+                ),
+                None => cell[d].div_rem_euclid(&C::from_ix(self.chunk_dim[d])),
+            };
+            chunk_index[d] = ch
+                .to_isize()
+                .and_then(|ch| Ic::try_from(ch).ok())
+                .ok_or(GridMapError::ChunkKeyOutOfRange { axis: d })?;
+            cell_index[d] = cl.to_ix();
+        }
+        Ok((chunk_index, Dim(cell_index)))
+    }
+}
 
-            let (ch, cl) = idx.div_rem_euclid(&dim);
-            chunk_index[i] = Ic::from(ch);
-            cell_index[i] = cl as Ix;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gridmap::bounding_box::BoundingBox;
+
+    /// Split the index with the euclidean division
+    fn expected(chunk_dim: &[Ix; 2], index: &[isize; 2]) -> ([isize; 2], Dim<[Ix; 2]>) {
+        let mut chunk_index = [0; 2];
+        let mut cell_index = [0; 2];
+        for d in 0..2 {
+            chunk_index[d] = index[d].div_euclid(chunk_dim[d] as isize);
+            cell_index[d] = index[d].rem_euclid(chunk_dim[d] as isize) as Ix;
         }
         (chunk_index, Dim(cell_index))
     }
+
+    #[test]
+    fn split_negative_index() {
+        for chunk_dim in [[4, 8], [3, 5], [1, 16]] {
+            let gridmap = GridMap::<u8, 2>::new(chunk_dim);
+            for x in -40..40 {
+                for y in [-17, -16, -9, -1, 0, 7, 16] {
+                    assert_eq!(
+                        gridmap.split_index(&[x, y]),
+                        expected(&chunk_dim, &[x, y]),
+                        "index {:?} with chunks {chunk_dim:?}",
+                        [x, y]
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn split_extreme_index() {
+        let gridmap = GridMap::<u8, 2>::new([4, 8]);
+        for index in [[isize::MIN, isize::MAX], [isize::MAX, isize::MIN]] {
+            assert_eq!(gridmap.split_index(&index), expected(&[4, 8], &index));
+        }
+
+        let gridmap = GridMap::<u8, 1, i8, i8>::new([4]);
+        assert_eq!(gridmap.split_index(&[-128]), ([-32], Dim([0])));
+        assert_eq!(gridmap.split_index(&[127]), ([31], Dim([3])));
+        assert_eq!(gridmap.split_index(&[-1]), ([-1], Dim([3])));
+    }
+
+    #[test]
+    fn split_errors() {
+        let gridmap = GridMap::<u8, 2, i8, i16>::new([2, 2]);
+        assert_eq!(
+            gridmap.try_split_index(&[0, 40000]),
+            Err(GridMapError::CoordinateOverflow { axis: 1 })
+        );
+        assert_eq!(
+            gridmap.try_split_index(&[-300, 0]),
+            Err(GridMapError::ChunkKeyOutOfRange { axis: 0 })
+        );
+
+        let mut gridmap = GridMap::<u8, 2>::new([2, 2]);
+        gridmap.set_limits(Some(BoundingBox {
+            start: [-4, -4],
+            end: [4, 4],
+        }));
+        assert!(gridmap.try_split_index(&[-4, 3]).is_ok());
+        assert_eq!(
+            gridmap.try_split_index(&[4, 0]),
+            Err(GridMapError::OutOfBounds)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "do not fit in the coordinate type")]
+    fn chunk_dim_overflows_coordinates() {
+        GridMap::<u8, 1, isize, i8>::new([200]);
+    }
+
+    #[test]
+    #[should_panic(expected = "do not fit in the coordinate type")]
+    fn power_of_two_chunk_dim_overflows_coordinates() {
+        GridMap::<u8, 2, isize, i8>::with_capacity([4, 256], 1);
+    }
+
+    #[test]
+    fn small_coordinates_near_the_edges() {
+        let mut gridmap = GridMap::<u8, 1, isize, i8>::new([100]);
+        assert_eq!(gridmap.split_index(&[-1]), ([-1], Dim([99])));
+        assert_eq!(gridmap.split_index(&[i8::MIN]), ([-2], Dim([72])));
+        assert_eq!(gridmap.split_index(&[i8::MAX]), ([1], Dim([27])));
+        gridmap.set(&[-1], 7);
+        gridmap.set(&[i8::MAX], 8);
+        assert_eq!(gridmap.get(&[-1]), 7);
+        assert_eq!(gridmap.get(&[i8::MAX]), 8);
+        assert_eq!(gridmap.len(), 2);
+
+        let mut gridmap = GridMap::<u8, 1, isize, i8>::new([64]);
+        for index in [i8::MIN, -65, -1, 0, 64, i8::MAX] {
+            gridmap.set(&[index], 1);
+        }
+        let mut cells: alloc::vec::Vec<_> = gridmap.indexed_iter().map(|(i, _)| i[0]).collect();
+        cells.sort_unstable();
+        assert_eq!(cells, [i8::MIN, -65, -1, 0, 64, i8::MAX]);
+
+        let gridmap = GridMap::<u8, 2, i16, i16>::new([1000, 4096]);
+        assert_eq!(
+            gridmap.split_index(&[i16::MIN, i16::MAX]),
+            ([-33, 7], Dim([232, 4095]))
+        );
+        assert_eq!(
+            gridmap.split_index(&[i16::MAX, i16::MIN]),
+            ([32, -8], Dim([767, 0]))
+        );
+    }
 }
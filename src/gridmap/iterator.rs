@@ -1,5 +1,6 @@
 //! Iterator over the cells in the GridMap
 
+use crate::{coordinate::Coordinate, gridmap::bounding_box::BoundingBox};
 use ndarray::{Dim, Dimension, IntoDimension, Ix};
 use num_traits::AsPrimitive;
 
@@ -15,19 +16,52 @@ pub mod indexed;
 /// Iterator over all non-empty cells within given boundaries with corresponding index
 pub mod bounded;
 
-/// Compute an index from a chunk index and a cell index.
+/// Order in which the chunks of the GridMap are visited
+pub mod order;
+
+/// Iterator over every cell within given boundaries, including null cells
+pub mod region;
+
+/// Iterator over the chunks of the GridMap
+pub mod chunks;
+
+/// Owning iterator over all non-empty cells with corresponding index
+pub mod owned;
+
+/// Cells of a chunk overlapping some boundaries
+mod window;
+
+/// Parallel iterators over the cells of the GridMap
+#[cfg(feature = "rayon")]
+pub mod parallel;
+
+/// Compute the index of the first cell of a chunk from the chunk index,
+/// shifting instead of multiplying if the chunk dimensions are powers of two.
+/// Panics if the index overflows the cell coordinate type.
 #[inline]
-fn from_chunk_to_cell_index<const D: usize, Ic>(
+pub(super) fn from_chunk_to_cell_index<const D: usize, Ic, C>(
     chunk_dim: &[Ix; D],
+    shift: &Option<[u32; D]>,
     chunk_index: &[Ic; D],
-) -> [isize; D]
+) -> [C; D]
 where
     Ic: AsPrimitive<isize>,
+    C: Coordinate,
 {
     // prepare an index to construct
-    let mut index = [0; D];
+    let mut index = [C::zero(); D];
     for d in 0..D {
-        index[d] = chunk_index[d].as_() * chunk_dim[d] as isize;
+        let c = chunk_index[d].as_();
+        let origin = num_traits::cast::<isize, C>(c).and_then(|c| match shift {
+            Some(shift) => {
+                let origin = c << shift[d] as usize;
+                (origin >> shift[d] as usize == c).then_some(origin)
+            }
+            None => c.checked_mul(&C::from_ix(chunk_dim[d])),
+        });
+        index[d] = origin.unwrap_or_else(|| {
+            panic!("origin of chunk coordinate {c} overflows the cell coordinate type")
+        });
     }
 
     index
@@ -35,21 +69,44 @@ where
 
 /// Compute an index from a chunk index and a cell index.
 #[inline]
-fn compute_cell_index<const D: usize>(
-    chunk_index: &[isize; D],
+fn compute_cell_index<const D: usize, C>(
+    chunk_index: &[C; D],
     cell_index: <Dim<[Ix; D]> as Dimension>::Pattern,
-) -> [isize; D]
+) -> [C; D]
 where
+    C: Coordinate,
     Dim<[Ix; D]>: Dimension,
 {
     // convert cell index into an indexable form
     let cell_index: Dim<[Ix; D]> = cell_index.into_dimension();
 
     // prepare an index to construct
-    let mut index = [0; D];
+    let mut index = [C::zero(); D];
     for d in 0..D {
-        index[d] = chunk_index[d] + cell_index[d] as isize;
+        index[d] = chunk_index[d] + C::from_ix(cell_index[d]);
     }
 
     index
 }
+
+/// Compute the bounding box of the chunk starting at the given index.
+#[inline]
+pub(super) fn chunk_bounds<const D: usize, C>(
+    chunk_dim: &[Ix; D],
+    origin: &[C; D],
+) -> BoundingBox<D, C>
+where
+    C: Coordinate,
+{
+    // prepare the two points
+    let mut start = [C::zero(); D];
+    let mut end = [C::zero(); D];
+
+    // for each dimension, the end saturates since it is exclusive
+    for d in 0..D {
+        start[d] = origin[d];
+        end[d] = origin[d].saturating_add(C::from_ix(chunk_dim[d]));
+    }
+
+    BoundingBox { start, end }
+}
@@ -1,179 +1,272 @@
 //! Iterator over all non-empty cells with corresponding index
 
-use super::{compute_cell_index, from_chunk_to_cell_index};
+use super::{
+    compute_cell_index, from_chunk_to_cell_index,
+    order::{ChunkOrder, Chunks, ChunksMut},
+    window::{Window, WindowMut, overlap},
+};
 use crate::{
     cell::Cell,
-    gridmap::{Chunk, GridMap, bounding_box::BoundingBox},
+    coordinate::Coordinate,
+    gridmap::{
+        GridMap,
+        bounding_box::{BoundingBox, indices::Indices},
+        slot::Slot,
+        try_chunk_key,
+    },
 };
-use ndarray::{Dim, Dimension, Ix};
+use core::hash::Hash;
+use hashbrown::HashMap;
+use ndarray::{Dim, Dimension, IntoDimension, Ix};
 use num_traits::{AsPrimitive, ConstZero};
 
 /// Get iterator over the grid map
-impl<A, const D: usize, Ic> GridMap<A, D, Ic>
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
 where
     A: Cell,
-    Ic: ConstZero,
+    Ic: ConstZero + AsPrimitive<isize>,
+    C: Coordinate,
 {
-    /// Create an iterator over all the cells of the chunks of the GridMap
-    pub fn bounded_iter(&self, bounds: BoundingBox<D>) -> Iter<'_, A, D, Ic> {
+    /// Create an iterator over the non-empty cells within the boundaries.
+    /// If the boundaries cover fewer chunks than the gridmap holds,
+    /// the chunks are looked up directly instead of scanning the whole gridmap.
+    pub fn bounded_iter(&self, bounds: BoundingBox<D, C>) -> Iter<'_, A, D, Ic, C>
+    where
+        Ic: Eq + Hash + TryFrom<isize>,
+    {
+        self.bounded_iter_in(bounds, self.order)
+    }
+
+    /// Create an iterator visiting the chunks in the given order
+    pub(crate) fn bounded_iter_in(
+        &self,
+        bounds: BoundingBox<D, C>,
+        order: Option<ChunkOrder>,
+    ) -> Iter<'_, A, D, Ic, C>
+    where
+        Ic: Eq + Hash + TryFrom<isize>,
+    {
+        // looking up the chunks visits them in lexicographic order,
+        // ranges too large to count are scanned
+        let range = bounds.chunk_range(&self.chunk_dim);
+        let lookup = range
+            .checked_volume()
+            .is_some_and(|volume| volume < self.map.len())
+            && order != Some(ChunkOrder::Morton);
         Iter {
             chunk_dim: self.chunk_dim,
-            chunks: self.map.iter(),
+            shift: self.shift,
+            chunks: if lookup {
+                BoundedChunks::Lookup {
+                    map: &self.map,
+                    keys: range.indices(),
+                }
+            } else {
+                BoundedChunks::Scan(Chunks::new(&self.map, order))
+            },
             cells: None,
-            cache: [0; D],
+            cache: [C::zero(); D],
             bounds,
         }
     }
 
-    /// Create an iterator over all the cells of the chunks of the GridMap
-    pub fn bounded_iter_mut(&mut self, bounds: BoundingBox<D>) -> IterMut<'_, A, D, Ic> {
+    /// Create a mutable iterator over the non-empty cells within the boundaries.
+    /// Only the chunks overlapping the boundaries and holding non-null cells
+    /// are converted to dense arrays, which stay dense until `compress` is called.
+    pub fn bounded_iter_mut(&mut self, bounds: BoundingBox<D, C>) -> IterMut<'_, A, D, Ic, C> {
+        // the cells may be modified in any way
+        if let Some(extents) = &mut self.extents {
+            extents.invalidate();
+        }
         IterMut {
             chunk_dim: self.chunk_dim,
-            chunks: self.map.iter_mut(),
+            shift: self.shift,
+            chunks: ChunksMut::new(&mut self.map, self.order),
             cells: None,
-            cache: [0; D],
+            cache: [C::zero(); D],
             bounds,
         }
     }
 }
 
+/// Chunks which may overlap the boundaries
+enum BoundedChunks<'i, A, const D: usize, Ic, C> {
+    /// Check every chunk of the gridmap
+    Scan(Chunks<'i, A, D, Ic>),
+
+    /// Look up the chunks covered by the boundaries
+    Lookup {
+        map: &'i HashMap<[Ic; D], Slot<A, D>>,
+        keys: Indices<D, C>,
+    },
+}
+
+/// Access next chunk
+impl<'i, A, const D: usize, Ic, C> Iterator for BoundedChunks<'i, A, D, Ic, C>
+where
+    Ic: Eq + Hash + TryFrom<isize>,
+    C: Coordinate,
+{
+    type Item = (&'i [Ic; D], &'i Slot<A, D>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Scan(chunks) => chunks.next(),
+            Self::Lookup { map, keys } => {
+                keys.find_map(|key| map.get_key_value(&try_chunk_key(&key)?))
+            }
+        }
+    }
+}
+
 /// Iterator over all the cells of the chunks of the GridMap
-pub struct Iter<'i, A, const D: usize, Ic = isize> {
+pub struct Iter<'i, A, const D: usize, Ic = isize, C = isize> {
     /// Dimensions of the chunks in the gridmap
     chunk_dim: [Ix; D],
 
+    /// Base 2 logarithm of the chunk dimensions if they are all powers of two
+    shift: Option<[u32; D]>,
+
     /// Iterator over the chunks
-    chunks: hashbrown::hash_map::Iter<'i, [Ic; D], Chunk<A, D>>,
+    chunks: BoundedChunks<'i, A, D, Ic, C>,
 
-    /// Iterator over the cells of the current chunk
-    cells: Option<ndarray::iter::IndexedIter<'i, A, Dim<[Ix; D]>>>,
+    /// Iterator over the overlapping cells of the current chunk
+    cells: Option<Window<'i, A, D>>,
 
-    /// Cache the index of the current chunk
-    cache: [isize; D],
+    /// Cache the index of the first overlapping cell of the current chunk
+    cache: [C; D],
 
     /// Boundaries to look for cells
-    bounds: BoundingBox<D>,
+    bounds: BoundingBox<D, C>,
 }
 
 /// Access next element of the iterator
-impl<'i, A, const D: usize, Ic> Iterator for Iter<'i, A, D, Ic>
+impl<'i, A, const D: usize, Ic, C> Iterator for Iter<'i, A, D, Ic, C>
 where
+    C: Coordinate,
     A: Cell,
-    Ic: AsPrimitive<isize>,
+    Ic: Eq + Hash + TryFrom<isize> + AsPrimitive<isize>,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
     Dim<[Ix; D]>: Dimension,
 {
-    type Item = ([isize; D], &'i A);
+    type Item = ([C; D], &'i A);
 
     fn next(&mut self) -> Option<Self::Item> {
-        'outer: loop {
+        loop {
             // Do we have an iterator over the cells of the current chunk?
             if let Some(cells) = &mut self.cells {
                 // Try to find a cell that is not null
-                for (cell_index, cell) in cells.by_ref() {
+                for (local, cell) in cells.by_ref() {
                     if !cell.is_null() {
-                        let index = compute_cell_index::<D>(&self.cache, cell_index);
-                        // TODO: create a view over the array to remove this check
-                        if self.bounds.contains(&index) {
-                            return Some((index, cell));
-                        }
+                        return Some((compute_cell_index(&self.cache, local), cell));
                     }
                 }
             }
 
             // Get an iterator over the next chunk
-            for (chunk_index, chunk) in &mut self.chunks {
-                let index = from_chunk_to_cell_index(&self.chunk_dim, chunk_index);
-                let bounds = chunk_bounds(&self.chunk_dim, &index);
-                if self.bounds.overlaps_with(&bounds) {
-                    self.cache = index;
-                    // TODO: create a view over the array
-                    self.cells = Some(chunk.indexed_iter());
-                    continue 'outer;
+            let (chunk_index, chunk) = self.chunks.next()?;
+            let origin = from_chunk_to_cell_index(&self.chunk_dim, &self.shift, chunk_index);
+            if let Some((start, size)) = overlap(&self.chunk_dim, &origin, &self.bounds) {
+                for d in 0..D {
+                    self.cache[d] = origin[d] + C::from_ix(start[d]);
                 }
+                self.cells = Some(Window::new(chunk.storage(), start, size));
             }
-
-            return None;
         }
     }
 }
 
 /// Mutable Iiterator over all the cells of the chunks of the GridMap
-pub struct IterMut<'i, A, const D: usize, Ic = isize> {
+pub struct IterMut<'i, A, const D: usize, Ic = isize, C = isize> {
     /// Dimensions of the chunks in the gridmap
     chunk_dim: [Ix; D],
 
+    /// Base 2 logarithm of the chunk dimensions if they are all powers of two
+    shift: Option<[u32; D]>,
+
     /// Iterator over the chunks
-    chunks: hashbrown::hash_map::IterMut<'i, [Ic; D], Chunk<A, D>>,
+    chunks: ChunksMut<'i, A, D, Ic>,
 
-    /// Iterator over the cells of the current chunk
-    cells: Option<ndarray::iter::IndexedIterMut<'i, A, Dim<[Ix; D]>>>,
+    /// Iterator over the overlapping cells of the current chunk
+    cells: Option<WindowMut<'i, A, D>>,
 
-    /// Cache the index of the current chunk
-    cache: [isize; D],
+    /// Cache the index of the first overlapping cell of the current chunk
+    cache: [C; D],
 
     /// Boundaries to look for cells
-    bounds: BoundingBox<D>,
+    bounds: BoundingBox<D, C>,
 }
 
 /// Access next element of the iterator
-impl<'i, A, const D: usize, Ic> Iterator for IterMut<'i, A, D, Ic>
+impl<'i, A, const D: usize, Ic, C> Iterator for IterMut<'i, A, D, Ic, C>
 where
-    A: Cell,
+    C: Coordinate,
+    A: Cell + Clone,
     Ic: AsPrimitive<isize>,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
     Dim<[Ix; D]>: Dimension,
 {
-    type Item = ([isize; D], &'i mut A);
+    type Item = ([C; D], &'i mut A);
 
     fn next(&mut self) -> Option<Self::Item> {
-        'outer: loop {
+        loop {
             // Do we have an iterator over the cells of the current chunk?
             if let Some(cells) = &mut self.cells {
                 // Try to find a cell that is not null
-                for (cell_index, cell) in cells.by_ref() {
+                for (local, cell) in cells.by_ref() {
                     if !cell.is_null() {
-                        let index = compute_cell_index::<D>(&self.cache, cell_index);
-                        // TODO: create a view over the array to remove this check
-                        if self.bounds.contains(&index) {
-                            return Some((index, cell));
-                        }
+                        return Some((compute_cell_index(&self.cache, local), cell));
                     }
                 }
             }
 
             // Get an iterator over the next chunk
-            for (chunk_index, chunk) in &mut self.chunks {
-                let index = from_chunk_to_cell_index(&self.chunk_dim, chunk_index);
-                let bounds = chunk_bounds(&self.chunk_dim, &index);
-                if self.bounds.overlaps_with(&bounds) {
-                    self.cache = index;
-                    // TODO: create a view over the array
-                    self.cells = Some(chunk.indexed_iter_mut());
-                    continue 'outer;
+            let (chunk_index, chunk) = self.chunks.next()?;
+            let origin = from_chunk_to_cell_index(&self.chunk_dim, &self.shift, chunk_index);
+            if let Some((start, size)) = overlap(&self.chunk_dim, &origin, &self.bounds)
+                && let Some(cells) = chunk.occupied_mut()
+            {
+                for d in 0..D {
+                    self.cache[d] = origin[d] + C::from_ix(start[d]);
                 }
+                self.cells = Some(WindowMut::new(cells, start, size));
             }
-
-            return None;
         }
     }
 }
 
-/// Compute the bounding box of the chunk
-#[inline]
-fn chunk_bounds<const D: usize>(chunk_dim: &[Ix; D], chunk_index: &[isize; D]) -> BoundingBox<D>
-where
-    Dim<[Ix; D]>: Dimension,
-{
-    // prepare the two points
-    let mut start = [0; D];
-    let mut end = [0; D];
-
-    // for each dimension
-    for i in 0..D {
-        let d = chunk_dim[i] as isize;
-        let s = chunk_index[i] * d;
-        start[i] = s;
-        end[i] = s + d;
+#[cfg(test)]
+mod tests {
+    use crate::gridmap::{GridMap, bounding_box::BoundingBox};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn very_large_bounds() {
+        let mut gridmap = GridMap::<u8, 2>::new([4, 4]);
+        gridmap.set(&[1, 2], 1);
+        gridmap.set(&[-5, 7], 2);
+
+        let bounds = BoundingBox {
+            start: [isize::MIN / 2; 2],
+            end: [isize::MAX / 2; 2],
+        };
+        let mut cells: Vec<_> = gridmap.bounded_iter(bounds).collect();
+        cells.sort_unstable();
+        assert_eq!(cells, [([-5, 7], &2), ([1, 2], &1)]);
     }
 
-    BoundingBox { start, end }
+    #[test]
+    fn small_bounds() {
+        let mut gridmap = GridMap::<u8, 2>::new([4, 4]);
+        for x in -8..8 {
+            gridmap.set(&[x, x], 1);
+        }
+
+        let bounds = BoundingBox {
+            start: [0, 0],
+            end: [2, 3],
+        };
+        let cells: Vec<_> = gridmap.bounded_iter(bounds).collect();
+        assert_eq!(cells, [([0, 0], &1), ([1, 1], &1)]);
+    }
 }
@@ -0,0 +1,249 @@
+//! Iterator over the chunks of the GridMap
+
+use super::{
+    chunk_bounds, from_chunk_to_cell_index,
+    order::{Chunks, ChunksMut},
+};
+use crate::{
+    cell::Cell,
+    coordinate::Coordinate,
+    gridmap::{GridMap, bounding_box::BoundingBox, slot::Slot, storage::Storage},
+};
+use core::marker::PhantomData;
+use ndarray::{ArrayViewMut, CowArray, Dim, Dimension, Ix};
+use num_traits::AsPrimitive;
+
+/// Get iterator over the chunks of the grid map
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
+where
+    A: Cell,
+    Ic: AsPrimitive<isize>,
+    C: Coordinate,
+{
+    /// Create an iterator over the chunks of the GridMap
+    pub fn chunks(&self) -> Iter<'_, A, D, Ic, C> {
+        Iter {
+            chunk_dim: self.chunk_dim,
+            shift: self.shift,
+            chunks: Chunks::new(&self.map, self.order),
+            coordinate: PhantomData,
+        }
+    }
+
+    /// Create a mutable iterator over the chunks of the GridMap
+    pub fn chunks_mut(&mut self) -> IterMut<'_, A, D, Ic, C> {
+        // the cells may be modified in any way
+        if let Some(extents) = &mut self.extents {
+            extents.invalidate();
+        }
+        IterMut {
+            chunk_dim: self.chunk_dim,
+            shift: self.shift,
+            chunks: ChunksMut::new(&mut self.map, self.order),
+            coordinate: PhantomData,
+        }
+    }
+}
+
+/// Chunk of the gridmap along with its position
+pub struct ChunkRef<'i, A, const D: usize, Ic = isize, C = isize> {
+    /// Index of the chunk
+    key: &'i [Ic; D],
+
+    /// Index of the first cell of the chunk
+    origin: [C; D],
+
+    /// Dimensions of the chunks in the gridmap
+    chunk_dim: [Ix; D],
+
+    /// Cells of the chunk
+    slot: &'i Slot<A, D>,
+}
+
+impl<'i, A, const D: usize, Ic, C> ChunkRef<'i, A, D, Ic, C>
+where
+    C: Coordinate,
+    A: Cell,
+    Dim<[Ix; D]>: Dimension,
+{
+    /// Index of the chunk
+    #[inline]
+    pub fn key(&self) -> &'i [Ic; D] {
+        self.key
+    }
+
+    /// Index of the first cell of the chunk
+    #[inline]
+    pub fn origin(&self) -> [C; D] {
+        self.origin
+    }
+
+    /// Boundaries of the chunk
+    #[inline]
+    pub fn bounds(&self) -> BoundingBox<D, C> {
+        chunk_bounds(&self.chunk_dim, &self.origin)
+    }
+
+    /// Number of non-null cells in the chunk
+    #[inline]
+    pub fn occupied(&self) -> usize {
+        self.slot.occupied()
+    }
+
+    /// Access the storage of the chunk
+    #[inline]
+    pub fn storage(&self) -> &'i Storage<A, D> {
+        self.slot.storage()
+    }
+
+    /// View the cells of the chunk as an array,
+    /// borrowed if the chunk is dense and built otherwise.
+    pub fn view(&self) -> CowArray<'i, A, Dim<[Ix; D]>>
+    where
+        A: Clone,
+    {
+        match self.slot.storage() {
+            Storage::Dense(chunk) => CowArray::from(chunk.view()),
+            storage => CowArray::from(storage.to_dense()),
+        }
+    }
+}
+
+/// Mutable chunk of the gridmap along with its position
+pub struct ChunkMut<'i, A, const D: usize, Ic = isize, C = isize> {
+    /// Index of the chunk
+    key: &'i [Ic; D],
+
+    /// Index of the first cell of the chunk
+    origin: [C; D],
+
+    /// Dimensions of the chunks in the gridmap
+    chunk_dim: [Ix; D],
+
+    /// Cells of the chunk
+    slot: &'i mut Slot<A, D>,
+}
+
+impl<A, const D: usize, Ic, C> ChunkMut<'_, A, D, Ic, C>
+where
+    C: Coordinate,
+    A: Cell,
+    Dim<[Ix; D]>: Dimension,
+{
+    /// Index of the chunk
+    #[inline]
+    pub fn key(&self) -> &[Ic; D] {
+        self.key
+    }
+
+    /// Index of the first cell of the chunk
+    #[inline]
+    pub fn origin(&self) -> [C; D] {
+        self.origin
+    }
+
+    /// Boundaries of the chunk
+    #[inline]
+    pub fn bounds(&self) -> BoundingBox<D, C> {
+        chunk_bounds(&self.chunk_dim, &self.origin)
+    }
+
+    /// Number of non-null cells in the chunk
+    #[inline]
+    pub fn occupied(&self) -> usize {
+        self.slot.occupied()
+    }
+
+    /// Access the storage of the chunk
+    #[inline]
+    pub fn storage(&self) -> &Storage<A, D> {
+        self.slot.storage()
+    }
+
+    /// Set a cell of the chunk, the index is relative to the origin of the chunk
+    #[inline]
+    pub fn set(&mut self, cell_index: &Dim<[Ix; D]>, cell: A)
+    where
+        A: PartialEq + Clone,
+    {
+        self.slot.set(cell_index, cell);
+    }
+
+    /// View the cells of the chunk as a mutable array,
+    /// the chunk is converted to a dense array if necessary.
+    #[inline]
+    pub fn view_mut(&mut self) -> ArrayViewMut<'_, A, Dim<[Ix; D]>>
+    where
+        A: Clone,
+    {
+        self.slot.storage_mut().dense_mut().view_mut()
+    }
+}
+
+/// Iterator over the chunks of the GridMap
+pub struct Iter<'i, A, const D: usize, Ic = isize, C = isize> {
+    /// Dimensions of the chunks in the gridmap
+    chunk_dim: [Ix; D],
+
+    /// Base 2 logarithm of the chunk dimensions if they are all powers of two
+    shift: Option<[u32; D]>,
+
+    /// Iterator over the chunks
+    chunks: Chunks<'i, A, D, Ic>,
+
+    /// Type of the cell coordinates
+    coordinate: PhantomData<C>,
+}
+
+/// Access next element of the iterator
+impl<'i, A, const D: usize, Ic, C> Iterator for Iter<'i, A, D, Ic, C>
+where
+    C: Coordinate,
+    Ic: AsPrimitive<isize>,
+{
+    type Item = ChunkRef<'i, A, D, Ic, C>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.chunks.next().map(|(key, slot)| ChunkRef {
+            key,
+            origin: from_chunk_to_cell_index(&self.chunk_dim, &self.shift, key),
+            chunk_dim: self.chunk_dim,
+            slot,
+        })
+    }
+}
+
+/// Mutable iterator over the chunks of the GridMap
+pub struct IterMut<'i, A, const D: usize, Ic = isize, C = isize> {
+    /// Dimensions of the chunks in the gridmap
+    chunk_dim: [Ix; D],
+
+    /// Base 2 logarithm of the chunk dimensions if they are all powers of two
+    shift: Option<[u32; D]>,
+
+    /// Iterator over the chunks
+    chunks: ChunksMut<'i, A, D, Ic>,
+
+    /// Type of the cell coordinates
+    coordinate: PhantomData<C>,
+}
+
+/// Access next element of the iterator
+impl<'i, A, const D: usize, Ic, C> Iterator for IterMut<'i, A, D, Ic, C>
+where
+    C: Coordinate,
+    Ic: AsPrimitive<isize>,
+{
+    type Item = ChunkMut<'i, A, D, Ic, C>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.chunks.next().map(|(key, slot)| ChunkMut {
+            key,
+            origin: from_chunk_to_cell_index(&self.chunk_dim, &self.shift, key),
+            chunk_dim: self.chunk_dim,
+            slot,
+        })
+    }
+}
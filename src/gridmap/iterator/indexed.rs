@@ -1,63 +1,85 @@
 //! Iterator over all non-empty cells with corresponding index
 
-use super::{compute_cell_index, from_chunk_to_cell_index};
+use super::{
+    compute_cell_index, from_chunk_to_cell_index,
+    order::{ChunkOrder, Chunks, ChunksMut},
+};
 use crate::{
     cell::Cell,
-    gridmap::{Chunk, GridMap},
+    coordinate::Coordinate,
+    gridmap::{GridMap, storage},
 };
 use ndarray::{Dim, Dimension, Ix};
 use num_traits::{AsPrimitive, ConstZero};
 
 /// Get iterator over the grid map
-impl<A, const D: usize, Ic> GridMap<A, D, Ic>
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
 where
-    Ic: ConstZero,
+    Ic: ConstZero + AsPrimitive<isize>,
     A: Cell,
+    C: Coordinate,
 {
     /// Create an iterator over all the cells of the chunks of the GridMap
-    pub fn indexed_iter(&self) -> Iter<'_, A, D, Ic> {
+    pub fn indexed_iter(&self) -> Iter<'_, A, D, Ic, C> {
+        self.indexed_iter_in(self.order)
+    }
+
+    /// Create an iterator visiting the chunks in the given order
+    pub(crate) fn indexed_iter_in(&self, order: Option<ChunkOrder>) -> Iter<'_, A, D, Ic, C> {
         Iter {
             chunk_dim: self.chunk_dim,
-            chunks: self.map.iter(),
+            shift: self.shift,
+            chunks: Chunks::new(&self.map, order),
             cells: None,
-            cache: [0; D],
+            cache: [C::zero(); D],
         }
     }
 
-    /// Create an iterator over all the cells of the chunks of the GridMap
-    pub fn indexed_iter_mut(&mut self) -> IterMut<'_, A, D, Ic> {
+    /// Create a mutable iterator over the non-empty cells of the GridMap with their index.
+    /// Chunks holding only null cells are skipped, the others are converted
+    /// to dense arrays which stay dense until `compress` is called.
+    pub fn indexed_iter_mut(&mut self) -> IterMut<'_, A, D, Ic, C> {
+        // the cells may be modified in any way
+        if let Some(extents) = &mut self.extents {
+            extents.invalidate();
+        }
         IterMut {
             chunk_dim: self.chunk_dim,
-            chunks: self.map.iter_mut(),
+            shift: self.shift,
+            chunks: ChunksMut::new(&mut self.map, self.order),
             cells: None,
-            cache: [0; D],
+            cache: [C::zero(); D],
         }
     }
 }
 
 /// Iterator over all the cells of the chunks of the GridMap
-pub struct Iter<'i, A, const D: usize, Ic = isize> {
+pub struct Iter<'i, A, const D: usize, Ic = isize, C = isize> {
     /// Dimensions of the chunks in the gridmap
     chunk_dim: [Ix; D],
 
+    /// Base 2 logarithm of the chunk dimensions if they are all powers of two
+    shift: Option<[u32; D]>,
+
     /// Iterator over the chunks
-    chunks: hashbrown::hash_map::Iter<'i, [Ic; D], Chunk<A, D>>,
+    chunks: Chunks<'i, A, D, Ic>,
 
     /// Iterator over the cells of the current chunk
-    cells: Option<ndarray::iter::IndexedIter<'i, A, Dim<[Ix; D]>>>,
+    cells: Option<storage::IndexedIter<'i, A, D>>,
 
     /// Cache the index of the current chunk in cell coordinates
-    cache: [isize; D],
+    cache: [C; D],
 }
 
 /// Access next element of the iterator
-impl<'i, A, const D: usize, Ic> Iterator for Iter<'i, A, D, Ic>
+impl<'i, A, const D: usize, Ic, C> Iterator for Iter<'i, A, D, Ic, C>
 where
+    C: Coordinate,
     A: Cell,
     Ic: AsPrimitive<isize>,
     Dim<[Ix; D]>: Dimension,
 {
-    type Item = ([isize; D], &'i A);
+    type Item = ([C; D], &'i A);
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
@@ -66,7 +88,7 @@ where
                 // Try to find a cell that is not null
                 for (cell_index, cell) in cells.by_ref() {
                     if !cell.is_null() {
-                        let index = compute_cell_index::<D>(&self.cache, cell_index);
+                        let index = compute_cell_index(&self.cache, cell_index);
                         return Some((index, cell));
                     }
                 }
@@ -74,8 +96,8 @@ where
 
             // Get an iterator over the next chunk
             if let Some((chunk_index, chunk)) = self.chunks.next() {
-                self.cache = from_chunk_to_cell_index(&self.chunk_dim, chunk_index);
-                self.cells = Some(chunk.indexed_iter());
+                self.cache = from_chunk_to_cell_index(&self.chunk_dim, &self.shift, chunk_index);
+                self.cells = Some(chunk.storage().indexed_iter());
             } else {
                 return None;
             }
@@ -84,28 +106,32 @@ where
 }
 
 /// Mutable Iiterator over all the cells of the chunks of the GridMap
-pub struct IterMut<'i, A, const D: usize, Ic = isize> {
+pub struct IterMut<'i, A, const D: usize, Ic = isize, C = isize> {
     /// Dimensions of the chunks in the gridmap
     chunk_dim: [Ix; D],
 
+    /// Base 2 logarithm of the chunk dimensions if they are all powers of two
+    shift: Option<[u32; D]>,
+
     /// Iterator over the chunks
-    chunks: hashbrown::hash_map::IterMut<'i, [Ic; D], Chunk<A, D>>,
+    chunks: ChunksMut<'i, A, D, Ic>,
 
     /// Iterator over the cells of the current chunk
     cells: Option<ndarray::iter::IndexedIterMut<'i, A, Dim<[Ix; D]>>>,
 
     /// Cache the index of the current chunk in cell coordinates
-    cache: [isize; D],
+    cache: [C; D],
 }
 
 /// Access next element of the iterator
-impl<'i, A, const D: usize, Ic> Iterator for IterMut<'i, A, D, Ic>
+impl<'i, A, const D: usize, Ic, C> Iterator for IterMut<'i, A, D, Ic, C>
 where
-    A: Cell,
+    C: Coordinate,
+    A: Cell + Clone,
     Ic: AsPrimitive<isize>,
     Dim<[Ix; D]>: Dimension,
 {
-    type Item = ([isize; D], &'i mut A);
+    type Item = ([C; D], &'i mut A);
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
@@ -114,7 +140,7 @@ where
                 // Try to find a cell that is not null
                 for (cell_index, cell) in cells.by_ref() {
                     if !cell.is_null() {
-                        let index = compute_cell_index::<D>(&self.cache, cell_index);
+                        let index = compute_cell_index(&self.cache, cell_index);
                         return Some((index, cell));
                     }
                 }
@@ -122,8 +148,8 @@ where
 
             // Get an iterator over the next chunk
             if let Some((chunk_index, chunk)) = self.chunks.next() {
-                self.cache = from_chunk_to_cell_index(&self.chunk_dim, chunk_index);
-                self.cells = Some(chunk.indexed_iter_mut());
+                self.cache = from_chunk_to_cell_index(&self.chunk_dim, &self.shift, chunk_index);
+                self.cells = chunk.occupied_mut().map(|cells| cells.indexed_iter_mut());
             } else {
                 return None;
             }
@@ -1,28 +1,37 @@
 //! Iterator over all non-empty cells of the GridMap
 
+use super::order::{Chunks, ChunksMut};
 use crate::{
     cell::Cell,
-    gridmap::{Chunk, GridMap},
+    gridmap::{GridMap, storage},
 };
 use ndarray::{Dim, Dimension, Ix};
+use num_traits::AsPrimitive;
 
 /// Get iterator over the grid map
-impl<A, const D: usize, Ic> GridMap<A, D, Ic>
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
 where
     A: Cell,
+    Ic: AsPrimitive<isize>,
 {
     /// Create an iterator over all the cells of the chunks of the GridMap
     pub fn iter(&self) -> Iter<'_, A, D, Ic> {
         Iter {
-            chunks: self.map.iter(),
+            chunks: Chunks::new(&self.map, self.order),
             cells: None,
         }
     }
 
-    /// Create an iterator over all the cells of the chunks of the GridMap
+    /// Create a mutable iterator over the non-empty cells of the GridMap.
+    /// Chunks holding only null cells are skipped, the others are converted
+    /// to dense arrays which stay dense until `compress` is called.
     pub fn iter_mut(&mut self) -> IterMut<'_, A, D, Ic> {
+        // the cells may be modified in any way
+        if let Some(extents) = &mut self.extents {
+            extents.invalidate();
+        }
         IterMut {
-            chunks: self.map.iter_mut(),
+            chunks: ChunksMut::new(&mut self.map, self.order),
             cells: None,
         }
     }
@@ -31,10 +40,10 @@ where
 /// Iterator over all the cells of the chunks of the GridMap
 pub struct Iter<'i, A, const D: usize, Ic = isize> {
     /// Iterator over the chunks
-    chunks: hashbrown::hash_map::Iter<'i, [Ic; D], Chunk<A, D>>,
+    chunks: Chunks<'i, A, D, Ic>,
 
     /// Iterator over the cells of the current chunk
-    cells: Option<ndarray::iter::Iter<'i, A, Dim<[Ix; D]>>>,
+    cells: Option<storage::Iter<'i, A, D>>,
 }
 
 /// Access next element of the iterator
@@ -59,7 +68,7 @@ where
 
             // Get an iterator over the next chunk
             if let Some((_, chunk)) = self.chunks.next() {
-                self.cells = Some(chunk.iter());
+                self.cells = Some(chunk.storage().iter());
             } else {
                 return None;
             }
@@ -70,7 +79,7 @@ where
 /// Mutable Iiterator over all the cells of the chunks of the GridMap
 pub struct IterMut<'i, A, const D: usize, Ic = isize> {
     /// Iterator over the chunks
-    chunks: hashbrown::hash_map::IterMut<'i, [Ic; D], Chunk<A, D>>,
+    chunks: ChunksMut<'i, A, D, Ic>,
 
     /// Iterator over the cells of the current chunk
     cells: Option<ndarray::iter::IterMut<'i, A, Dim<[Ix; D]>>>,
@@ -79,7 +88,7 @@ pub struct IterMut<'i, A, const D: usize, Ic = isize> {
 /// Access next element of the iterator
 impl<'i, A, const D: usize, Ic> Iterator for IterMut<'i, A, D, Ic>
 where
-    A: Cell,
+    A: Cell + Clone,
     Dim<[Ix; D]>: Dimension,
 {
     type Item = &'i mut A;
@@ -98,10 +107,57 @@ where
 
             // Get an iterator over the next chunk
             if let Some((_, chunk)) = self.chunks.next() {
-                self.cells = Some(chunk.iter_mut());
+                self.cells = chunk.occupied_mut().map(|cells| cells.iter_mut());
             } else {
                 return None;
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::gridmap::{GridMap, bounding_box::BoundingBox, slot::Slot, storage::Storage};
+
+    #[test]
+    fn iter_mut() {
+        let mut gridmap = GridMap::<u8, 2>::new([4, 4]);
+        gridmap.set(&[1, 2], 3);
+        gridmap.set(&[-7, 5], 9);
+        gridmap.iter_mut().for_each(|cell| *cell += 1);
+        assert_eq!(gridmap.get(&[1, 2]), 4);
+        assert_eq!(gridmap.get(&[-7, 5]), 10);
+        assert_eq!(gridmap.get(&[0, 0]), 0);
+        assert_eq!(gridmap.len(), 2);
+    }
+
+    #[test]
+    fn uniform_chunks() {
+        let mut gridmap = GridMap::<u8, 2>::new([4, 4]);
+        let bounds = BoundingBox {
+            start: [0, 0],
+            end: [8, 8],
+        };
+        gridmap.fill(&bounds, 1);
+        gridmap.iter_mut().for_each(|cell| *cell = 2);
+        assert_eq!(gridmap.len(), 64);
+        gridmap.compress();
+        assert!(
+            gridmap
+                .map
+                .values()
+                .all(|chunk| matches!(chunk.storage(), Storage::Uniform { cell: 2, .. }))
+        );
+    }
+
+    #[test]
+    fn null_chunks_stay_compact() {
+        let mut chunk = Slot::<u8, 2>::empty(Storage::new(&[4, 4]));
+        assert!(chunk.occupied_mut().is_none());
+        assert!(matches!(chunk.storage(), Storage::Uniform { .. }));
+
+        let mut chunk = Slot::<u8, 2>::new(Storage::uniform(&[4, 4], 1));
+        assert!(chunk.occupied_mut().is_some());
+        assert!(matches!(chunk.storage(), Storage::Dense(_)));
+    }
+}
@@ -0,0 +1,227 @@
+//! Order in which the chunks of the GridMap are visited
+
+use crate::{
+    cell::Cell,
+    gridmap::{GridMap, slot::Slot},
+};
+use alloc::vec::{self, Vec};
+use core::cmp::Ordering;
+use hashbrown::{HashMap, hash_map};
+use num_traits::AsPrimitive;
+
+/// Order in which the chunks are visited by the iterators
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum ChunkOrder {
+    /// Sort the chunks by their index, the first component being the most significant
+    #[default]
+    Lexicographic,
+
+    /// Sort the chunks along a Z-order curve by interleaving the bits of their index
+    Morton,
+}
+
+impl ChunkOrder {
+    /// Compare two chunk indexes
+    pub fn compare<Ic, const D: usize>(&self, a: &[Ic; D], b: &[Ic; D]) -> Ordering
+    where
+        Ic: AsPrimitive<isize>,
+    {
+        match self {
+            Self::Lexicographic => {
+                for d in 0..D {
+                    match a[d].as_().cmp(&b[d].as_()) {
+                        Ordering::Equal => continue,
+                        ordering => return ordering,
+                    }
+                }
+                Ordering::Equal
+            }
+            Self::Morton => {
+                // Find the component with the most significant differing bit
+                // and compare the two indexes along that component.
+                let mut dim = 0;
+                let mut diff = 0;
+                for d in 0..D {
+                    let y = to_unsigned(a[d].as_()) ^ to_unsigned(b[d].as_());
+                    if diff < y && diff < (diff ^ y) {
+                        dim = d;
+                        diff = y;
+                    }
+                }
+                to_unsigned(a[dim].as_()).cmp(&to_unsigned(b[dim].as_()))
+            }
+        }
+    }
+}
+
+/// Map a signed component to an unsigned one while preserving the order
+#[inline]
+fn to_unsigned(value: isize) -> usize {
+    (value as usize) ^ (1 << (usize::BITS - 1))
+}
+
+/// Define the order of iteration of the gridmap
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
+where
+    A: Cell,
+{
+    /// Order in which the iterators visit the chunks,
+    /// `None` if they follow the order of the internal hashmap.
+    #[inline]
+    pub fn order(&self) -> Option<ChunkOrder> {
+        self.order
+    }
+
+    /// Set the order in which the iterators visit the chunks.
+    /// Sorting the chunks has a cost each time an iterator is created.
+    #[inline]
+    pub fn set_order(&mut self, order: Option<ChunkOrder>) {
+        self.order = order;
+    }
+
+    /// Order to follow where the result must not depend on the hashmap,
+    /// lexicographic unless another order was set.
+    #[inline]
+    pub(crate) fn sorted_order(&self) -> Option<ChunkOrder> {
+        Some(self.order.unwrap_or_default())
+    }
+}
+
+/// Iterator over the chunks of the gridmap
+pub(crate) enum Chunks<'i, A, const D: usize, Ic> {
+    /// Follow the order of the hashmap
+    Unordered(hash_map::Iter<'i, [Ic; D], Slot<A, D>>),
+
+    /// Follow a defined order
+    Ordered(vec::IntoIter<(&'i [Ic; D], &'i Slot<A, D>)>),
+}
+
+impl<'i, A, const D: usize, Ic> Chunks<'i, A, D, Ic> {
+    /// Iterate over the chunks in the given order
+    pub(crate) fn new(map: &'i HashMap<[Ic; D], Slot<A, D>>, order: Option<ChunkOrder>) -> Self
+    where
+        Ic: AsPrimitive<isize>,
+    {
+        match order {
+            None => Self::Unordered(map.iter()),
+            Some(order) => {
+                let mut chunks: Vec<_> = map.iter().collect();
+                chunks.sort_unstable_by(|a, b| order.compare(a.0, b.0));
+                Self::Ordered(chunks.into_iter())
+            }
+        }
+    }
+}
+
+/// Access next chunk
+impl<'i, A, const D: usize, Ic> Iterator for Chunks<'i, A, D, Ic> {
+    type Item = (&'i [Ic; D], &'i Slot<A, D>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Unordered(chunks) => chunks.next(),
+            Self::Ordered(chunks) => chunks.next(),
+        }
+    }
+}
+
+/// Mutable iterator over the chunks of the gridmap
+pub(crate) enum ChunksMut<'i, A, const D: usize, Ic> {
+    /// Follow the order of the hashmap
+    Unordered(hash_map::IterMut<'i, [Ic; D], Slot<A, D>>),
+
+    /// Follow a defined order
+    Ordered(vec::IntoIter<(&'i [Ic; D], &'i mut Slot<A, D>)>),
+}
+
+impl<'i, A, const D: usize, Ic> ChunksMut<'i, A, D, Ic> {
+    /// Iterate over the chunks in the given order
+    pub(crate) fn new(map: &'i mut HashMap<[Ic; D], Slot<A, D>>, order: Option<ChunkOrder>) -> Self
+    where
+        Ic: AsPrimitive<isize>,
+    {
+        match order {
+            None => Self::Unordered(map.iter_mut()),
+            Some(order) => {
+                let mut chunks: Vec<_> = map.iter_mut().collect();
+                chunks.sort_unstable_by(|a, b| order.compare(a.0, b.0));
+                Self::Ordered(chunks.into_iter())
+            }
+        }
+    }
+}
+
+/// Access next chunk
+impl<'i, A, const D: usize, Ic> Iterator for ChunksMut<'i, A, D, Ic> {
+    type Item = (&'i [Ic; D], &'i mut Slot<A, D>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Unordered(chunks) => chunks.next(),
+            Self::Ordered(chunks) => chunks.next(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Interleave the bits of the components, the first component being the most significant
+    fn morton_code(index: &[isize; 2]) -> u128 {
+        let mut code = 0;
+        for bit in (0..usize::BITS).rev() {
+            for &component in index {
+                code = (code << 1) | ((to_unsigned(component) >> bit) & 1) as u128;
+            }
+        }
+        code
+    }
+
+    fn keys() -> Vec<[isize; 2]> {
+        let mut keys = Vec::new();
+        for x in -5..5 {
+            for y in -5..5 {
+                keys.push([x, y]);
+            }
+        }
+        keys.extend([[isize::MIN, 0], [isize::MAX, -1], [-1, isize::MAX]]);
+        keys
+    }
+
+    #[test]
+    fn lexicographic() {
+        let mut keys = keys();
+        keys.sort_unstable_by(|a, b| ChunkOrder::Lexicographic.compare(a, b));
+        let mut expected = self::keys();
+        expected.sort_unstable();
+        assert_eq!(keys, expected);
+    }
+
+    #[test]
+    fn morton_negative_keys() {
+        let mut keys = keys();
+        keys.sort_unstable_by(|a, b| ChunkOrder::Morton.compare(a, b));
+        let mut expected = self::keys();
+        expected.sort_unstable_by_key(morton_code);
+        assert_eq!(keys, expected);
+
+        // the quadrants around the origin follow the Z-order curve
+        let mut keys = [[0, 0], [-1, 0], [0, -1], [-1, -1]];
+        keys.sort_unstable_by(|a, b| ChunkOrder::Morton.compare(a, b));
+        assert_eq!(keys, [[-1, -1], [-1, 0], [0, -1], [0, 0]]);
+    }
+
+    #[test]
+    fn gridmap_order() {
+        let mut gridmap = GridMap::<u8, 2>::new([2, 2]);
+        for key in [[0, 0], [-1, 0], [0, -1], [-1, -1], [-2, 1]] {
+            gridmap.set(&[key[0] * 2, key[1] * 2], 1);
+        }
+        gridmap.set_order(Some(ChunkOrder::Morton));
+        let indices: Vec<_> = gridmap.indexed_iter().map(|(index, _)| index).collect();
+        assert_eq!(indices, [[-2, -2], [-4, 2], [-2, 0], [0, -2], [0, 0]]);
+    }
+}
@@ -0,0 +1,156 @@
+//! Owning iterator over all non-empty cells with corresponding index
+
+use super::{compute_cell_index, from_chunk_to_cell_index};
+use crate::{
+    cell::Cell,
+    coordinate::Coordinate,
+    gridmap::{GridMap, slot::Slot},
+};
+use alloc::vec::{self, Vec};
+use core::iter::Zip;
+use ndarray::{Dim, Dimension, Ix, iter::IndicesIter};
+use num_traits::AsPrimitive;
+
+/// Consume the gridmap into its non-empty cells
+impl<A, const D: usize, Ic, C> IntoIterator for GridMap<A, D, Ic, C>
+where
+    A: Cell + Clone,
+    Ic: AsPrimitive<isize>,
+    C: Coordinate,
+    Dim<[Ix; D]>: Dimension,
+{
+    type Item = ([C; D], A);
+    type IntoIter = IntoIter<A, D, Ic, C>;
+
+    /// Create an iterator over the non-empty cells, following the order of the gridmap
+    fn into_iter(self) -> Self::IntoIter {
+        let mut chunks: Vec<_> = self.map.into_iter().collect();
+        if let Some(order) = self.order {
+            chunks.sort_unstable_by(|a, b| order.compare(&a.0, &b.0));
+        }
+        IntoIter {
+            chunk_dim: self.chunk_dim,
+            shift: self.shift,
+            chunks: chunks.into_iter(),
+            cells: None,
+            cache: [C::zero(); D],
+        }
+    }
+}
+
+/// Cells of a chunk along with their index in the chunk
+type Cells<A, const D: usize> =
+    Zip<IndicesIter<Dim<[Ix; D]>>, ndarray::iter::IntoIter<A, Dim<[Ix; D]>>>;
+
+/// Owning iterator over all the non-empty cells of the GridMap
+pub struct IntoIter<A, const D: usize, Ic = isize, C = isize>
+where
+    Dim<[Ix; D]>: Dimension,
+{
+    /// Dimensions of the chunks in the gridmap
+    chunk_dim: [Ix; D],
+
+    /// Base 2 logarithm of the chunk dimensions if they are all powers of two
+    shift: Option<[u32; D]>,
+
+    /// Iterator over the chunks
+    chunks: vec::IntoIter<([Ic; D], Slot<A, D>)>,
+
+    /// Iterator over the cells of the current chunk
+    cells: Option<Cells<A, D>>,
+
+    /// Cache the index of the current chunk in cell coordinates
+    cache: [C; D],
+}
+
+/// Access next element of the iterator
+impl<A, const D: usize, Ic, C> Iterator for IntoIter<A, D, Ic, C>
+where
+    C: Coordinate,
+    A: Cell + Clone,
+    Ic: AsPrimitive<isize>,
+    Dim<[Ix; D]>: Dimension,
+{
+    type Item = ([C; D], A);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Do we have an iterator over the cells of the current chunk?
+            if let Some(cells) = &mut self.cells {
+                // Try to find a cell that is not null
+                for (cell_index, cell) in cells.by_ref() {
+                    if !cell.is_null() {
+                        let index = compute_cell_index(&self.cache, cell_index);
+                        return Some((index, cell));
+                    }
+                }
+            }
+
+            // Get an iterator over the next chunk, skipping the chunks without any cell
+            let (chunk_index, chunk) = self.chunks.find(|(_, chunk)| chunk.occupied() != 0)?;
+            self.cache = from_chunk_to_cell_index(&self.chunk_dim, &self.shift, &chunk_index);
+            let cells = chunk.into_storage().into_dense();
+            self.cells = Some(ndarray::indices(cells.raw_dim()).into_iter().zip(cells));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gridmap::{GridMap, bounding_box::BoundingBox, iterator::order::ChunkOrder};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn collect_round_trip() {
+        let mut cells: Vec<([isize; 2], u8)> = (-20..20)
+            .filter(|x| x % 3 != 0)
+            .map(|x| ([x, 7 - 2 * x], (x + 21) as u8))
+            .collect();
+        let gridmap: GridMap<u8, 2> = cells.iter().copied().collect();
+        assert_eq!(gridmap.len(), cells.len());
+
+        let mut collected: Vec<_> = gridmap.clone().into_iter().collect();
+        collected.sort_unstable();
+        cells.sort_unstable();
+        assert_eq!(collected, cells);
+        assert_eq!(collected.into_iter().collect::<GridMap<u8, 2>>(), gridmap);
+    }
+
+    #[test]
+    fn into_iter_follows_the_order() {
+        let mut gridmap = GridMap::<u8, 2>::new([2, 2]);
+        gridmap.fill(
+            &BoundingBox {
+                start: [-2, -2],
+                end: [2, 2],
+            },
+            1,
+        );
+        // a chunk left without any cell is skipped
+        gridmap.set(&[9, 9], 2);
+        gridmap.set(&[9, 9], 0);
+        gridmap.set_order(Some(ChunkOrder::Lexicographic));
+        let cells: Vec<_> = gridmap.into_iter().map(|(index, _)| index).collect();
+        assert_eq!(
+            cells,
+            [
+                [-2, -2],
+                [-2, -1],
+                [-1, -2],
+                [-1, -1],
+                [-2, 0],
+                [-2, 1],
+                [-1, 0],
+                [-1, 1],
+                [0, -2],
+                [0, -1],
+                [1, -2],
+                [1, -1],
+                [0, 0],
+                [0, 1],
+                [1, 0],
+                [1, 1],
+            ]
+        );
+    }
+}
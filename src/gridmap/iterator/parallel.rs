@@ -0,0 +1,160 @@
+//! Parallel iterators over the cells of the GridMap
+
+use super::{chunk_bounds, compute_cell_index, from_chunk_to_cell_index};
+use crate::{
+    cell::Cell,
+    coordinate::Coordinate,
+    gridmap::{GridMap, bounding_box::BoundingBox},
+};
+use core::hash::Hash;
+use ndarray::{Dim, Dimension, Ix};
+use num_traits::AsPrimitive;
+use rayon::prelude::*;
+
+/// Get parallel iterators over the grid map.
+/// The work is split per chunk, thus the cells are not visited in any defined order.
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
+where
+    A: Cell,
+    C: Coordinate,
+{
+    /// Create a parallel iterator over all non-empty cells of the GridMap
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = &A>
+    where
+        A: Send + Sync,
+        Ic: Sync,
+        Dim<[Ix; D]>: Dimension,
+    {
+        self.map
+            .par_iter()
+            .flat_map_iter(|(_, chunk)| chunk.storage().iter().filter(|cell| !cell.is_null()))
+    }
+
+    /// Create a mutable parallel iterator over all non-empty cells of the GridMap.
+    /// Chunks holding only null cells are skipped, the others are converted
+    /// to dense arrays which stay dense until `compress` is called.
+    pub fn par_iter_mut(&mut self) -> impl ParallelIterator<Item = &mut A>
+    where
+        A: Clone + Send + Sync,
+        Ic: Sync,
+        Dim<[Ix; D]>: Dimension,
+    {
+        // the cells may be modified in any way
+        if let Some(extents) = &mut self.extents {
+            extents.invalidate();
+        }
+        self.map.par_iter_mut().flat_map_iter(|(_, chunk)| {
+            chunk
+                .occupied_mut()
+                .into_iter()
+                .flat_map(|cells| cells.iter_mut())
+                .filter(|cell| !cell.is_null())
+        })
+    }
+
+    /// Create a parallel iterator over all non-empty cells with corresponding index
+    pub fn par_indexed_iter(&self) -> impl ParallelIterator<Item = ([C; D], &A)>
+    where
+        A: Send + Sync,
+        Ic: Sync + AsPrimitive<isize>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        let chunk_dim = self.chunk_dim;
+        let shift = self.shift;
+        self.map
+            .par_iter()
+            .flat_map_iter(move |(chunk_index, chunk)| {
+                let origin = from_chunk_to_cell_index(&chunk_dim, &shift, chunk_index);
+                chunk
+                    .storage()
+                    .indexed_iter()
+                    .filter(|(_, cell)| !cell.is_null())
+                    .map(move |(cell_index, cell)| (compute_cell_index(&origin, cell_index), cell))
+            })
+    }
+
+    /// Create a mutable parallel iterator over all non-empty cells with corresponding index.
+    /// Chunks holding only null cells are skipped, the others are converted
+    /// to dense arrays which stay dense until `compress` is called.
+    pub fn par_indexed_iter_mut(&mut self) -> impl ParallelIterator<Item = ([C; D], &mut A)>
+    where
+        A: Clone + Send + Sync,
+        Ic: Sync + AsPrimitive<isize>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        // the cells may be modified in any way
+        if let Some(extents) = &mut self.extents {
+            extents.invalidate();
+        }
+        let chunk_dim = self.chunk_dim;
+        let shift = self.shift;
+        self.map
+            .par_iter_mut()
+            .flat_map_iter(move |(chunk_index, chunk)| {
+                let origin = from_chunk_to_cell_index(&chunk_dim, &shift, chunk_index);
+                chunk
+                    .occupied_mut()
+                    .into_iter()
+                    .flat_map(|cells| cells.indexed_iter_mut())
+                    .filter(|(_, cell)| !cell.is_null())
+                    .map(move |(cell_index, cell)| (compute_cell_index(&origin, cell_index), cell))
+            })
+    }
+
+    /// Create a mutable parallel iterator over all non-empty cells
+    /// within given boundaries with corresponding index.
+    /// Only the chunks overlapping the boundaries and holding non-null cells
+    /// are converted to dense arrays, which stay dense until `compress` is called.
+    pub fn par_bounded_iter_mut(
+        &mut self,
+        bounds: BoundingBox<D, C>,
+    ) -> impl ParallelIterator<Item = ([C; D], &mut A)>
+    where
+        A: Clone + Send + Sync,
+        Ic: Sync + AsPrimitive<isize>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        // the cells may be modified in any way
+        if let Some(extents) = &mut self.extents {
+            extents.invalidate();
+        }
+        let chunk_dim = self.chunk_dim;
+        let shift = self.shift;
+        self.map
+            .par_iter_mut()
+            .filter(move |(chunk_index, _)| {
+                let origin = from_chunk_to_cell_index(&chunk_dim, &shift, chunk_index);
+                bounds.overlaps_with(&chunk_bounds(&chunk_dim, &origin))
+            })
+            .flat_map_iter(move |(chunk_index, chunk)| {
+                let origin = from_chunk_to_cell_index(&chunk_dim, &shift, chunk_index);
+                chunk
+                    .occupied_mut()
+                    .into_iter()
+                    .flat_map(|cells| cells.indexed_iter_mut())
+                    .filter(|(_, cell)| !cell.is_null())
+                    .map(move |(cell_index, cell)| (compute_cell_index(&origin, cell_index), cell))
+                    .filter(move |(index, _)| bounds.contains(index))
+            })
+    }
+
+    /// Remove chunks which only contain empty cells,
+    /// the cells are counted in parallel.
+    pub fn par_prune(&mut self)
+    where
+        A: Send + Sync,
+        Ic: Eq + Hash + Sync + AsPrimitive<isize>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        self.map.par_iter_mut().for_each(|(_, chunk)| {
+            chunk.occupied();
+        });
+        self.map.retain(|chunk_index, chunk| {
+            let keep = chunk.occupied() != 0;
+            if !keep && let Some(extents) = &mut self.extents {
+                extents.remove_chunk(chunk_index);
+            }
+            keep
+        });
+    }
+}
@@ -0,0 +1,310 @@
+//! Iterator over every cell within given boundaries, including null cells
+
+use super::{
+    compute_cell_index, from_chunk_to_cell_index,
+    order::ChunkOrder,
+    window::{Window, WindowMut, overlap},
+};
+use crate::{
+    cell::Cell,
+    coordinate::Coordinate,
+    gridmap::{
+        GridMap,
+        bounding_box::{BoundingBox, indices::Indices},
+        chunk_key_component,
+        slot::Slot,
+        storage::Storage,
+        try_chunk_key,
+    },
+};
+use alloc::vec::{self, Vec};
+use core::hash::Hash;
+use hashbrown::{HashMap, hash_map::Entry};
+use ndarray::{Dim, Dimension, IntoDimension, Ix};
+use num_traits::AsPrimitive;
+
+/// Get iterator over a region of the grid map
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
+where
+    A: Cell,
+    Ic: Eq + Hash + TryFrom<isize> + AsPrimitive<isize>,
+    C: Coordinate,
+{
+    /// Create an iterator over every cell within the boundaries, null cells included.
+    /// The chunks are visited in lexicographic order and the cells of each chunk
+    /// in lexicographic order, cells of missing chunks are the empty cell.
+    pub fn region_iter(&self, bounds: BoundingBox<D, C>) -> Iter<'_, A, D, Ic, C> {
+        Iter {
+            chunk_dim: self.chunk_dim,
+            shift: self.shift,
+            map: &self.map,
+            empty: &self.empty,
+            keys: bounds.chunk_range(&self.chunk_dim).indices(),
+            cells: None,
+            cache: [C::zero(); D],
+            bounds,
+        }
+    }
+
+    /// Create a mutable iterator over every cell within the boundaries, null cells included.
+    /// The cells are visited in the same order as `region_iter`,
+    /// the missing chunks are created and the chunks are converted to dense arrays.
+    /// Chunks left empty can be freed with `prune`.
+    /// If the boundaries cover fewer chunks than the gridmap holds,
+    /// the chunks are looked up directly instead of scanning the whole gridmap.
+    pub fn region_iter_mut(&mut self, bounds: BoundingBox<D, C>) -> IterMut<'_, A, D, Ic, C>
+    where
+        [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        // the cells may be modified in any way
+        if let Some(extents) = &mut self.extents {
+            extents.invalidate();
+        }
+
+        // create the missing chunks
+        let range = bounds.chunk_range(&self.chunk_dim);
+        for key in range.indices() {
+            if let Entry::Vacant(entry) = self.map.entry(key.map(chunk_key_component)) {
+                if let Some(extents) = &mut self.extents {
+                    extents.insert_chunk(entry.key());
+                }
+                entry.insert(Slot::empty(Storage::new(&self.chunk_dim)));
+            }
+        }
+
+        // collect the chunks covered by the boundaries in order,
+        // looking them up visits them in lexicographic order
+        let lookup = range
+            .checked_volume()
+            .is_some_and(|volume| volume < self.map.len());
+        let chunks: Vec<_> = if lookup {
+            range
+                .indices()
+                .filter_map(|key| {
+                    let (key, slot) = self.map.get_key_value_mut(&try_chunk_key(&key)?)?;
+                    Some((key as *const [Ic; D], slot as *mut Slot<A, D>))
+                })
+                .collect::<Vec<_>>()
+                .into_iter()
+                // SAFETY: the keys of the range are distinct, so are the chunks they point to,
+                // and the gridmap stays mutably borrowed for as long as the references live
+                .map(|(key, slot)| unsafe { (&*key, &mut *slot) })
+                .collect()
+        } else {
+            let keys = BoundingBox::<D> {
+                start: range.start.map(AsPrimitive::as_),
+                end: range.end.map(AsPrimitive::as_),
+            };
+            let mut chunks: Vec<_> = self
+                .map
+                .iter_mut()
+                .filter(|(key, _)| keys.contains(&key.map(AsPrimitive::as_)))
+                .collect();
+            chunks.sort_unstable_by(|a, b| ChunkOrder::Lexicographic.compare(a.0, b.0));
+            chunks
+        };
+
+        IterMut {
+            chunk_dim: self.chunk_dim,
+            shift: self.shift,
+            chunks: chunks.into_iter(),
+            cells: None,
+            cache: [C::zero(); D],
+            bounds,
+        }
+    }
+}
+
+/// Iterator over every cell within given boundaries
+pub struct Iter<'i, A, const D: usize, Ic = isize, C = isize> {
+    /// Dimensions of the chunks in the gridmap
+    chunk_dim: [Ix; D],
+
+    /// Base 2 logarithm of the chunk dimensions if they are all powers of two
+    shift: Option<[u32; D]>,
+
+    /// Chunks of the gridmap
+    map: &'i HashMap<[Ic; D], Slot<A, D>>,
+
+    /// Empty cell for missing chunks
+    empty: &'i A,
+
+    /// Indices of the chunks covered by the boundaries
+    keys: Indices<D, C>,
+
+    /// Iterator over the overlapping cells of the current chunk
+    cells: Option<Window<'i, A, D>>,
+
+    /// Cache the index of the first overlapping cell of the current chunk
+    cache: [C; D],
+
+    /// Boundaries of the region
+    bounds: BoundingBox<D, C>,
+}
+
+/// Access next element of the iterator
+impl<'i, A, const D: usize, Ic, C> Iterator for Iter<'i, A, D, Ic, C>
+where
+    C: Coordinate,
+    A: Cell,
+    Ic: Eq + Hash + TryFrom<isize>,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    type Item = ([C; D], &'i A);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Get the next cell from the current chunk
+            if let Some((local, cell)) = self.cells.as_mut().and_then(|cells| cells.next()) {
+                return Some((compute_cell_index(&self.cache, local), cell));
+            }
+
+            // Get an iterator over the next chunk, which always overlaps the region
+            let key = self.keys.next()?;
+            let origin = from_chunk_to_cell_index(&self.chunk_dim, &self.shift, &key);
+            let (start, size) = overlap(&self.chunk_dim, &origin, &self.bounds)?;
+            for d in 0..D {
+                self.cache[d] = origin[d] + C::from_ix(start[d]);
+            }
+            self.cells = Some(
+                match try_chunk_key(&key).and_then(|key| self.map.get(&key)) {
+                    Some(chunk) => Window::new(chunk.storage(), start, size),
+                    None => Window::uniform(self.empty, size),
+                },
+            );
+        }
+    }
+}
+
+/// Mutable iterator over every cell within given boundaries
+pub struct IterMut<'i, A, const D: usize, Ic = isize, C = isize> {
+    /// Dimensions of the chunks in the gridmap
+    chunk_dim: [Ix; D],
+
+    /// Base 2 logarithm of the chunk dimensions if they are all powers of two
+    shift: Option<[u32; D]>,
+
+    /// Chunks covered by the boundaries in order
+    chunks: vec::IntoIter<(&'i [Ic; D], &'i mut Slot<A, D>)>,
+
+    /// Iterator over the overlapping cells of the current chunk
+    cells: Option<WindowMut<'i, A, D>>,
+
+    /// Cache the index of the first overlapping cell of the current chunk
+    cache: [C; D],
+
+    /// Boundaries of the region
+    bounds: BoundingBox<D, C>,
+}
+
+/// Access next element of the iterator
+impl<'i, A, const D: usize, Ic, C> Iterator for IterMut<'i, A, D, Ic, C>
+where
+    C: Coordinate,
+    A: Cell + Clone,
+    Ic: AsPrimitive<isize>,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    type Item = ([C; D], &'i mut A);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Get the next cell from the current chunk
+            if let Some((local, cell)) = self.cells.as_mut().and_then(|cells| cells.next()) {
+                return Some((compute_cell_index(&self.cache, local), cell));
+            }
+
+            // Get an iterator over the next chunk, which always overlaps the region
+            let (chunk_index, chunk) = self.chunks.next()?;
+            let origin = from_chunk_to_cell_index(&self.chunk_dim, &self.shift, chunk_index);
+            let (start, size) = overlap(&self.chunk_dim, &origin, &self.bounds)?;
+            for d in 0..D {
+                self.cache[d] = origin[d] + C::from_ix(start[d]);
+            }
+            self.cells = Some(WindowMut::new(chunk.storage_mut().dense_mut(), start, size));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gridmap::{GridMap, bounding_box::BoundingBox};
+    use alloc::vec::Vec;
+
+    /// Gridmap with a cell on the diagonal of every chunk from -12 to 12
+    fn diagonal() -> GridMap<u8, 2> {
+        let mut gridmap = GridMap::new([4, 3]);
+        for x in -12_i8..12 {
+            gridmap.set(&[x, x], x.unsigned_abs() + 1);
+        }
+        gridmap
+    }
+
+    #[test]
+    fn visits_every_cell() {
+        let gridmap = diagonal();
+        let bounds = BoundingBox {
+            start: [-5, -6],
+            end: [2, 1],
+        };
+        let cells: Vec<_> = gridmap.region_iter(bounds).collect();
+        assert_eq!(cells.len(), bounds.volume());
+
+        // every cell is visited once, missing chunks yield null cells
+        let mut indices: Vec<_> = cells.iter().map(|(index, _)| *index).collect();
+        indices.sort_unstable();
+        assert_eq!(indices, bounds.indices().collect::<Vec<_>>());
+        for (index, &cell) in cells {
+            assert_eq!(cell, gridmap.get(&index));
+        }
+    }
+
+    #[test]
+    fn mutate_few_chunks() {
+        // the region covers fewer chunks than the gridmap holds
+        let mut gridmap = diagonal();
+        let chunks = gridmap.map.len();
+        let bounds = BoundingBox {
+            start: [-1, -2],
+            end: [1, 2],
+        };
+        let expected: Vec<_> = gridmap.region_iter(bounds).map(|(i, c)| (i, *c)).collect();
+        let visited: Vec<_> = gridmap
+            .region_iter_mut(bounds)
+            .map(|(index, cell)| {
+                *cell += 10;
+                (index, *cell - 10)
+            })
+            .collect();
+        assert_eq!(visited, expected);
+        assert_eq!(gridmap.get(&[0, 0]), 11);
+        assert_eq!(gridmap.get(&[-1, 1]), 10);
+        assert_eq!(gridmap.get(&[1, 1]), 2);
+        assert_eq!(gridmap.map.len(), chunks + 2);
+    }
+
+    #[test]
+    fn mutate_many_chunks() {
+        // the region covers more chunks than the gridmap holds
+        let mut gridmap = diagonal();
+        let bounds = BoundingBox {
+            start: [-14, -15],
+            end: [13, 14],
+        };
+        let expected: Vec<_> = gridmap.region_iter(bounds).map(|(i, c)| (i, *c)).collect();
+        let visited: Vec<_> = gridmap
+            .region_iter_mut(bounds)
+            .map(|(index, cell)| (index, *cell))
+            .collect();
+        assert_eq!(visited, expected);
+
+        gridmap
+            .region_iter_mut(bounds)
+            .for_each(|(_, cell)| *cell = 0);
+        gridmap.prune();
+        assert_eq!(gridmap.map.len(), 0);
+    }
+}
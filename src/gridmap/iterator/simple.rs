@@ -1,28 +1,36 @@
 //! Iterator over all the cells of the chunks of the GridMap
 
+use super::order::{Chunks, ChunksMut};
 use crate::{
     cell::Cell,
-    gridmap::{Chunk, GridMap},
+    gridmap::{GridMap, storage},
 };
 use ndarray::{Dim, Dimension, Ix};
+use num_traits::AsPrimitive;
 
 /// Get iterator over the grid map
-impl<A, const D: usize, Ic> GridMap<A, D, Ic>
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
 where
     A: Cell,
+    Ic: AsPrimitive<isize>,
 {
     /// Create an iterator over all the cells of the chunks of the GridMap
     pub fn raw_iter(&self) -> Iter<'_, A, D, Ic> {
         Iter {
-            chunks: self.map.iter(),
+            chunks: Chunks::new(&self.map, self.order),
             cells: None,
         }
     }
 
-    /// Create an iterator over all the cells of the chunks of the GridMap
+    /// Create a mutable iterator over all the cells of the chunks of the GridMap.
+    /// Every chunk is converted to a dense array which stays dense until `compress` is called.
     pub fn raw_iter_mut(&mut self) -> IterMut<'_, A, D, Ic> {
+        // the cells may be modified in any way
+        if let Some(extents) = &mut self.extents {
+            extents.invalidate();
+        }
         IterMut {
-            chunks: self.map.iter_mut(),
+            chunks: ChunksMut::new(&mut self.map, self.order),
             cells: None,
         }
     }
@@ -31,10 +39,10 @@ where
 /// Iterator over all the cells of the chunks of the GridMap
 pub struct Iter<'i, A, const D: usize, Ic = isize> {
     /// Iterator over the chunks
-    chunks: hashbrown::hash_map::Iter<'i, [Ic; D], Chunk<A, D>>,
+    chunks: Chunks<'i, A, D, Ic>,
 
     /// Iterator over the cells of the current chunk
-    cells: Option<ndarray::iter::Iter<'i, A, Dim<[Ix; D]>>>,
+    cells: Option<storage::Iter<'i, A, D>>,
 }
 
 /// Access next element of the iterator
@@ -54,7 +62,7 @@ where
 
             // Get an iterator over the next chunk
             if let Some((_, chunk)) = self.chunks.next() {
-                self.cells = Some(chunk.iter());
+                self.cells = Some(chunk.storage().iter());
             } else {
                 return None;
             }
@@ -65,7 +73,7 @@ where
 /// Mutable Iiterator over all the cells of the chunks of the GridMap
 pub struct IterMut<'i, A, const D: usize, Ic = isize> {
     /// Iterator over the chunks
-    chunks: hashbrown::hash_map::IterMut<'i, [Ic; D], Chunk<A, D>>,
+    chunks: ChunksMut<'i, A, D, Ic>,
 
     /// Iterator over the cells of the current chunk
     cells: Option<ndarray::iter::IterMut<'i, A, Dim<[Ix; D]>>>,
@@ -74,7 +82,7 @@ pub struct IterMut<'i, A, const D: usize, Ic = isize> {
 /// Access next element of the iterator
 impl<'i, A, const D: usize, Ic> Iterator for IterMut<'i, A, D, Ic>
 where
-    A: Cell,
+    A: Cell + Clone,
     Dim<[Ix; D]>: Dimension,
 {
     type Item = &'i mut A;
@@ -88,7 +96,7 @@ where
 
             // Get an iterator over the next chunk
             if let Some((_, chunk)) = self.chunks.next() {
-                self.cells = Some(chunk.iter_mut());
+                self.cells = Some(chunk.storage_mut().dense_mut().iter_mut());
             } else {
                 return None;
             }
@@ -0,0 +1,158 @@
+//! Cells of a chunk overlapping some boundaries
+
+use super::chunk_bounds;
+use crate::{
+    Chunk,
+    coordinate::Coordinate,
+    gridmap::{bounding_box::BoundingBox, storage::Storage},
+};
+use ndarray::{Dim, Dimension, IntoDimension, Ix, Slice, iter::IndicesIter};
+
+/// Cells of a chunk overlapping some boundaries
+pub(super) struct Window<'i, A, const D: usize> {
+    /// Indices of the overlapping cells relative to the first one
+    indices: IndicesIter<Dim<[Ix; D]>>,
+
+    /// Access to the overlapping cells
+    cells: Cells<'i, A, D>,
+}
+
+/// Access to the overlapping cells of each kind of storage
+enum Cells<'i, A, const D: usize> {
+    /// View over the overlapping part of a dense array
+    Dense(ndarray::iter::Iter<'i, A, Dim<[Ix; D]>>),
+
+    /// Palette storage, accessed index by index
+    Palette {
+        storage: &'i Storage<A, D>,
+        start: [Ix; D],
+    },
+
+    /// The same cell everywhere
+    Uniform(&'i A),
+}
+
+impl<'i, A, const D: usize> Window<'i, A, D>
+where
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    /// Iterate over the cells of the storage starting at `start`
+    pub(super) fn new(storage: &'i Storage<A, D>, start: [Ix; D], size: [Ix; D]) -> Self {
+        let cells = match storage {
+            Storage::Dense(chunk) => Cells::Dense(
+                chunk
+                    .slice_each_axis(|axis| {
+                        let d = axis.axis.index();
+                        Slice::from(start[d]..start[d] + size[d])
+                    })
+                    .into_iter(),
+            ),
+            Storage::Palette(_) => Cells::Palette { storage, start },
+            Storage::Uniform { cell, .. } => Cells::Uniform(cell),
+        };
+        Self {
+            indices: ndarray::indices(size).into_iter(),
+            cells,
+        }
+    }
+
+    /// Repeat the same cell over the given number of cells
+    pub(super) fn uniform(cell: &'i A, size: [Ix; D]) -> Self {
+        Self {
+            indices: ndarray::indices(size).into_iter(),
+            cells: Cells::Uniform(cell),
+        }
+    }
+}
+
+/// Access next cell along with its index relative to the first one
+impl<'i, A, const D: usize> Iterator for Window<'i, A, D>
+where
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    type Item = (<Dim<[Ix; D]> as Dimension>::Pattern, &'i A);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let local = self.indices.next()?;
+        let cell = match &mut self.cells {
+            Cells::Dense(cells) => cells.next()?,
+            Cells::Palette { storage, start } => {
+                let offset = local.clone().into_dimension();
+                let mut cell_index = *start;
+                for d in 0..D {
+                    cell_index[d] += offset[d];
+                }
+                &storage[Dim(cell_index)]
+            }
+            Cells::Uniform(cell) => cell,
+        };
+        Some((local, cell))
+    }
+}
+
+/// Mutable cells of a chunk overlapping some boundaries
+pub(super) struct WindowMut<'i, A, const D: usize> {
+    /// Indices of the overlapping cells relative to the first one
+    indices: IndicesIter<Dim<[Ix; D]>>,
+
+    /// View over the overlapping part of the dense array
+    cells: ndarray::iter::IterMut<'i, A, Dim<[Ix; D]>>,
+}
+
+impl<'i, A, const D: usize> WindowMut<'i, A, D>
+where
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    /// Iterate over the cells of the dense array starting at `start`
+    pub(super) fn new(chunk: &'i mut Chunk<A, D>, start: [Ix; D], size: [Ix; D]) -> Self {
+        Self {
+            indices: ndarray::indices(size).into_iter(),
+            cells: chunk
+                .slice_each_axis_mut(|axis| {
+                    let d = axis.axis.index();
+                    Slice::from(start[d]..start[d] + size[d])
+                })
+                .into_iter(),
+        }
+    }
+}
+
+/// Access next cell along with its index relative to the first one
+impl<'i, A, const D: usize> Iterator for WindowMut<'i, A, D>
+where
+    Dim<[Ix; D]>: Dimension,
+{
+    type Item = (<Dim<[Ix; D]> as Dimension>::Pattern, &'i mut A);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let local = self.indices.next()?;
+        self.cells.next().map(|cell| (local, cell))
+    }
+}
+
+/// Compute the first cell and the number of cells of the chunk overlapping the boundaries
+#[inline]
+pub(super) fn overlap<const D: usize, C>(
+    chunk_dim: &[Ix; D],
+    origin: &[C; D],
+    bounds: &BoundingBox<D, C>,
+) -> Option<([Ix; D], [Ix; D])>
+where
+    C: Coordinate,
+{
+    let overlap = chunk_bounds(chunk_dim, origin).intersection(bounds)?;
+
+    // convert the overlap to the coordinates of the chunk
+    let mut start = [0; D];
+    let mut size = [0; D];
+    for d in 0..D {
+        start[d] = (overlap.start[d] - origin[d]).to_ix();
+        size[d] = (overlap.end[d] - overlap.start[d]).to_ix();
+    }
+    Some((start, size))
+}
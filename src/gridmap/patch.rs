@@ -0,0 +1,295 @@
+//! Difference between two GridMaps which can be applied and reverted
+
+use super::{GridMap, iterator::order::ChunkOrder, slot::Slot, storage::Storage};
+use crate::{cell::Cell, coordinate::Coordinate};
+use alloc::vec::Vec;
+use core::hash::Hash;
+use ndarray::{Dim, Dimension, IntoDimension, Ix};
+use num_traits::AsPrimitive;
+
+/// Change of a chunk from one gridmap to another
+#[derive(Clone)]
+pub enum ChunkChange<A, const D: usize> {
+    /// The chunk was added with the given cells
+    Added(Storage<A, D>),
+
+    /// The chunk was removed, the cells it held are kept to revert the change
+    Removed(Storage<A, D>),
+
+    /// Some cells of the chunk changed, listed with their former and new values
+    Cells(Vec<(Dim<[Ix; D]>, A, A)>),
+}
+
+impl<A, const D: usize> ChunkChange<A, D>
+where
+    A: Clone,
+{
+    /// Create the change reverting this one
+    pub fn invert(&self) -> Self {
+        match self {
+            Self::Added(storage) => Self::Removed(storage.clone()),
+            Self::Removed(storage) => Self::Added(storage.clone()),
+            Self::Cells(cells) => Self::Cells(
+                cells
+                    .iter()
+                    .map(|(index, old, new)| (*index, new.clone(), old.clone()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Changes turning a gridmap into another one, ordered by chunk index
+#[derive(Clone)]
+pub struct Patch<A, const D: usize, Ic = isize> {
+    /// Dimensions of the chunks of both gridmaps
+    chunk_dim: [Ix; D],
+
+    /// Change of each chunk which differs
+    changes: Vec<([Ic; D], ChunkChange<A, D>)>,
+}
+
+impl<A, const D: usize, Ic> Patch<A, D, Ic> {
+    /// Create a patch from the change of each chunk, sorting them by chunk index
+    pub(super) fn new(chunk_dim: [Ix; D], mut changes: Vec<([Ic; D], ChunkChange<A, D>)>) -> Self
+    where
+        Ic: AsPrimitive<isize>,
+    {
+        // the order does not depend on the hashmaps
+        changes.sort_unstable_by(|a, b| ChunkOrder::Lexicographic.compare(&a.0, &b.0));
+        Self { chunk_dim, changes }
+    }
+
+    /// Dimensions of the chunks the patch applies to
+    #[inline]
+    pub fn chunk_dim(&self) -> &[Ix; D] {
+        &self.chunk_dim
+    }
+
+    /// Change of each chunk which differs, ordered by chunk index
+    #[inline]
+    pub fn changes(&self) -> &[([Ic; D], ChunkChange<A, D>)] {
+        &self.changes
+    }
+
+    /// Returns true if the patch does not change anything
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Number of cells recorded by the patch,
+    /// whole chunks counting their non-null cells
+    pub fn size(&self) -> usize
+    where
+        A: Cell,
+        Dim<[Ix; D]>: Dimension,
+    {
+        self.changes
+            .iter()
+            .map(|(_, change)| match change {
+                ChunkChange::Added(storage) | ChunkChange::Removed(storage) => storage.occupied(),
+                ChunkChange::Cells(cells) => 2 * cells.len(),
+            })
+            .sum()
+    }
+
+    /// Create the patch reverting this one
+    pub fn invert(&self) -> Self
+    where
+        A: Clone,
+        Ic: Copy,
+    {
+        Self {
+            chunk_dim: self.chunk_dim,
+            changes: self
+                .changes
+                .iter()
+                .map(|(key, change)| (*key, change.invert()))
+                .collect(),
+        }
+    }
+}
+
+/// Compute and apply patches
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
+where
+    A: Cell,
+    C: Coordinate,
+{
+    /// Compute the changes turning this gridmap into the other one.
+    /// Chunks with the same index are compared cell against cell,
+    /// chunks only present in one of the gridmaps are recorded whole.
+    /// Panics if the chunk dimensions of the gridmaps differ.
+    pub fn diff(&self, other: &Self) -> Patch<A, D, Ic>
+    where
+        A: PartialEq + Clone,
+        Ic: Eq + Hash + AsPrimitive<isize>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        assert_eq!(
+            self.chunk_dim, other.chunk_dim,
+            "the chunk dimensions of both gridmaps must match"
+        );
+
+        // chunks of this gridmap, then the ones only found in the other gridmap
+        let keys = self
+            .map
+            .keys()
+            .chain(other.map.keys().filter(|key| !self.map.contains_key(*key)));
+        let changes = keys
+            .filter_map(|key| Some((*key, chunk_change(self.map.get(key), other.map.get(key))?)))
+            .collect();
+        Patch::new(self.chunk_dim, changes)
+    }
+
+    /// Apply the changes of the patch to the gridmap.
+    /// Panics if the chunk dimensions of the patch differ from the ones of the gridmap.
+    pub fn apply(&mut self, patch: &Patch<A, D, Ic>)
+    where
+        A: PartialEq + Clone,
+        Ic: Eq + Hash + AsPrimitive<isize>,
+        [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        assert_eq!(
+            self.chunk_dim, patch.chunk_dim,
+            "the chunk dimensions of the patch must match the gridmap"
+        );
+
+        for (key, change) in &patch.changes {
+            match change {
+                ChunkChange::Added(storage) => {
+                    if let Some(extents) = &mut self.extents {
+                        if !self.map.contains_key(key) {
+                            extents.insert_chunk(key);
+                        }
+                        extents.touch_chunk(&self.chunk_dim, &self.shift, key);
+                    }
+                    self.map.insert(*key, Slot::new(storage.clone()));
+                }
+                ChunkChange::Removed(_) => {
+                    if self.map.remove(key).is_some()
+                        && let Some(extents) = &mut self.extents
+                    {
+                        extents.touch_chunk(&self.chunk_dim, &self.shift, key);
+                        extents.remove_chunk(key);
+                    }
+                }
+                ChunkChange::Cells(cells) => {
+                    for (cell_index, _, cell) in cells {
+                        self.set_chunk_cell(*key, cell_index, cell.clone());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Change turning a chunk into another one, `None` if they hold the same cells.
+/// Missing chunks and chunks with only null cells are treated alike.
+pub(super) fn chunk_change<A, const D: usize>(
+    old: Option<&Slot<A, D>>,
+    new: Option<&Slot<A, D>>,
+) -> Option<ChunkChange<A, D>>
+where
+    A: Cell + PartialEq + Clone,
+    Dim<[Ix; D]>: Dimension,
+{
+    let old = old.filter(|chunk| chunk.occupied() != 0);
+    let new = new.filter(|chunk| chunk.occupied() != 0);
+    match (old, new) {
+        (None, None) => None,
+        (None, Some(new)) => Some(ChunkChange::Added(new.storage().clone())),
+        (Some(old), None) => Some(ChunkChange::Removed(old.storage().clone())),
+        (Some(old), Some(new)) => {
+            // compare the chunks cell against cell
+            let cells: Vec<_> = old
+                .storage()
+                .indexed_iter()
+                .zip(new.storage().iter())
+                .filter(|((_, old), new)| old != new)
+                .map(|((index, old), new)| (index.into_dimension(), old.clone(), new.clone()))
+                .collect();
+            (!cells.is_empty()).then_some(ChunkChange::Cells(cells))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> GridMap<u8, 2> {
+        let mut gridmap = GridMap::new([4, 4]);
+        gridmap.set(&[1, 2], 3);
+        gridmap.set(&[1, 3], 3);
+        gridmap.set(&[-7, 5], 9);
+        gridmap
+    }
+
+    #[test]
+    fn diff_and_apply() {
+        let old = sample();
+        let mut new = sample();
+        new.set(&[1, 2], 4);
+        new.set(&[-7, 5], 0);
+        new.set(&[-9, -9], 1);
+
+        let patch = old.diff(&new);
+        assert_eq!(patch.changes().len(), 3);
+        assert!(matches!(
+            patch.changes()[0],
+            ([-3, -3], ChunkChange::Added(_))
+        ));
+        assert!(matches!(
+            patch.changes()[1],
+            ([-2, 1], ChunkChange::Removed(_))
+        ));
+        match &patch.changes()[2] {
+            ([0, 0], ChunkChange::Cells(cells)) => {
+                assert_eq!(cells.as_slice(), &[(Dim([1, 2]), 3, 4)]);
+            }
+            _ => panic!("expected the cells of chunk [0, 0] to change"),
+        }
+
+        let mut patched = old.clone();
+        patched.apply(&patch);
+        assert_eq!(patched, new);
+    }
+
+    #[test]
+    fn invert() {
+        let old = sample();
+        let mut new = sample();
+        new.set(&[1, 3], 0);
+        new.set(&[20, 20], 2);
+
+        let patch = old.diff(&new);
+        let mut patched = new.clone();
+        patched.apply(&patch.invert());
+        assert_eq!(patched, old);
+        assert_eq!(new.diff(&old).changes().len(), patch.changes().len());
+    }
+
+    #[test]
+    fn identical_gridmaps() {
+        let old = sample();
+        let mut new = sample();
+        assert!(old.diff(&new).is_empty());
+
+        // chunks holding only null cells are the same as missing chunks
+        new.set(&[50, 50], 1);
+        new.iter_mut()
+            .filter(|cell| **cell == 1)
+            .for_each(|cell| *cell = 0);
+        assert!(old.diff(&new).is_empty());
+        assert_eq!(old.diff(&new).size(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn chunk_dim_mismatch() {
+        sample().diff(&GridMap::new([2, 2]));
+    }
+}
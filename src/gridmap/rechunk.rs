@@ -0,0 +1,130 @@
+//! Change the dimensions of the chunks of the GridMap
+
+use super::{
+    GridMap,
+    dense::WriteMode,
+    iterator::{chunk_bounds, from_chunk_to_cell_index},
+    storage::Storage,
+};
+use crate::{cell::Cell, coordinate::Coordinate};
+use core::hash::Hash;
+use ndarray::{Dim, Dimension, IntoDimension, Ix};
+use num_traits::{AsPrimitive, ConstZero};
+
+/// Change the dimensions of the chunks
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
+where
+    A: Cell,
+    C: Coordinate,
+{
+    /// Create a gridmap with the same cells stored in chunks of the given dimensions.
+    /// The cells are copied block by block and chunks without any non-null cell are dropped.
+    /// The order of the chunks, the tracking of the boundaries and the limits are kept.
+    pub fn rechunk(&self, chunk_dim: [Ix; D]) -> Self
+    where
+        A: PartialEq + Clone,
+        Ic: Eq + Hash + ConstZero + TryFrom<isize> + AsPrimitive<isize>,
+        [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+        Dim<[Ix; D]>: Dimension,
+    {
+        let mut target = Self::new(chunk_dim);
+        target.order = self.order;
+        target.limits = self.limits;
+        target.track_boundaries(self.is_tracking_boundaries());
+
+        // the chunks do not overlap, so null cells never need to be written
+        for (chunk_index, chunk) in &self.map {
+            if chunk.occupied() == 0 {
+                continue;
+            }
+            let origin: [C; D] =
+                from_chunk_to_cell_index(&self.chunk_dim, &self.shift, chunk_index);
+            match chunk.storage() {
+                Storage::Dense(cells) => {
+                    target.write_dense(&origin, cells.view(), WriteMode::SkipNull);
+                }
+                Storage::Uniform { cell, .. } => {
+                    let bounds = chunk_bounds(&self.chunk_dim, &origin);
+                    target.fill(&bounds, cell.clone());
+                }
+                storage => {
+                    target.write_dense(&origin, storage.to_dense().view(), WriteMode::SkipNull);
+                }
+            }
+        }
+
+        target
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gridmap::{GridMap, bounding_box::BoundingBox};
+    use alloc::vec::Vec;
+
+    /// Sorted non-null cells of the gridmap
+    fn cells(gridmap: &GridMap<u16, 2>) -> Vec<([isize; 2], u16)> {
+        let mut cells: Vec<_> = gridmap.indexed_iter().map(|(i, &c)| (i, c)).collect();
+        cells.sort_unstable();
+        cells
+    }
+
+    /// Gridmap mixing dense, sparse and uniform chunks around the origin
+    fn source(chunk_dim: [usize; 2], track: bool) -> GridMap<u16, 2> {
+        let mut gridmap = GridMap::new(chunk_dim);
+        gridmap.track_boundaries(track);
+        gridmap.fill(
+            &BoundingBox {
+                start: [-16, 0],
+                end: [-8, 8],
+            },
+            7,
+        );
+        for x in -13..11 {
+            for y in -9..6 {
+                if (x * 3 + y * 5) % 4 == 0 {
+                    gridmap.set(&[x, y], (x * 100 + y + 2000) as u16);
+                }
+            }
+        }
+        gridmap.set(&[-40, 33], 1);
+        gridmap
+    }
+
+    #[test]
+    fn rechunk() {
+        for track in [false, true] {
+            for (from, to) in [([4, 4], [5, 3]), ([5, 3], [8, 2]), ([3, 7], [16, 16])] {
+                let gridmap = source(from, track);
+                let rechunked = gridmap.rechunk(to);
+                assert_eq!(rechunked.chunk_dim, to);
+                assert_eq!(rechunked.is_tracking_boundaries(), track);
+                assert_eq!(cells(&rechunked), cells(&gridmap));
+                assert_eq!(rechunked.len(), gridmap.len());
+                assert_eq!(rechunked.boundaries(), gridmap.boundaries());
+                assert_eq!(
+                    rechunked.boundaries(),
+                    Some(BoundingBox {
+                        start: [-40, -9],
+                        end: [11, 34],
+                    })
+                );
+                assert_eq!(rechunked.rechunk(from), gridmap);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_chunks_are_dropped() {
+        let mut gridmap = source([4, 4], true);
+        gridmap.indexed_iter_mut().for_each(|(index, cell)| {
+            if index[0] < 0 {
+                *cell = 0;
+            }
+        });
+        let rechunked = gridmap.rechunk([2, 8]);
+        assert_eq!(cells(&rechunked), cells(&gridmap));
+        assert!(rechunked.map.keys().all(|key| key[0] >= 0));
+        assert_eq!(rechunked.boundaries(), gridmap.boundaries());
+    }
+}
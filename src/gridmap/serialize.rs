@@ -0,0 +1,201 @@
+//! Serialization of the GridMap
+
+use super::{
+    GridMap, chunk_dim_fits,
+    iterator::order::{self, ChunkOrder},
+    slot::Slot,
+    storage::Storage,
+};
+use crate::{Chunk, cell::Cell, coordinate::Coordinate};
+use alloc::vec::Vec;
+use core::hash::Hash;
+use hashbrown::HashMap;
+use ndarray::{Dim, Dimension, Ix};
+use num_traits::AsPrimitive;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error, ser::SerializeStruct};
+
+/// Serialize the GridMap, skipping the chunks containing only null cells
+impl<A, const D: usize, Ic, C> Serialize for GridMap<A, D, Ic, C>
+where
+    A: Cell + Clone,
+    Ic: Serialize + AsPrimitive<isize>,
+    [Ix; D]: Serialize,
+    [Ic; D]: Serialize,
+    Chunk<A, D>: Serialize,
+    Dim<[Ix; D]>: Dimension,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("GridMap", 2)?;
+        state.serialize_field("chunk_dim", &self.chunk_dim)?;
+        state.serialize_field(
+            "chunks",
+            &Chunks {
+                map: &self.map,
+                order: self.sorted_order(),
+            },
+        )?;
+        state.end()
+    }
+}
+
+/// Sequence of the non-empty chunks of the GridMap
+struct Chunks<'m, A, const D: usize, Ic> {
+    /// Chunks of the gridmap
+    map: &'m HashMap<[Ic; D], Slot<A, D>>,
+
+    /// Order in which the chunks are written
+    order: Option<ChunkOrder>,
+}
+
+/// Serialize the chunks as a sequence of pairs of chunk index and chunk
+impl<A, const D: usize, Ic> Serialize for Chunks<'_, A, D, Ic>
+where
+    A: Cell + Clone,
+    Ic: AsPrimitive<isize>,
+    [Ic; D]: Serialize,
+    Chunk<A, D>: Serialize,
+    Dim<[Ix; D]>: Dimension,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(
+            order::Chunks::new(self.map, self.order)
+                .filter(|(_, chunk)| chunk.occupied() != 0)
+                .map(|(chunk_index, chunk)| (chunk_index, chunk.storage())),
+        )
+    }
+}
+
+/// Serialize the storage as a dense array whatever its representation
+impl<A, const D: usize> Serialize for Storage<A, D>
+where
+    A: Clone,
+    Chunk<A, D>: Serialize,
+    Dim<[Ix; D]>: Dimension,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Dense(chunk) => chunk.serialize(serializer),
+            _ => self.to_dense().serialize(serializer),
+        }
+    }
+}
+
+/// Intermediate representation of the GridMap to deserialize
+#[derive(Deserialize)]
+#[serde(rename = "GridMap")]
+#[serde(bound(
+    deserialize = "[Ix; D]: Deserialize<'de>, [Ic; D]: Deserialize<'de>, Chunk<A, D>: Deserialize<'de>"
+))]
+struct Repr<A, const D: usize, Ic> {
+    /// Dimensions of the chunks in the gridmap
+    chunk_dim: [Ix; D],
+
+    /// Pairs of chunk index and chunk
+    chunks: Vec<([Ic; D], Chunk<A, D>)>,
+}
+
+/// Deserialize the GridMap, rejecting chunks which do not match the chunk dimensions
+impl<'de, A, const D: usize, Ic, C> Deserialize<'de> for GridMap<A, D, Ic, C>
+where
+    A: Cell,
+    C: Coordinate,
+    Ic: Eq + Hash,
+    [Ix; D]: Deserialize<'de>,
+    [Ic; D]: Deserialize<'de>,
+    Chunk<A, D>: Deserialize<'de>,
+    Dim<[Ix; D]>: Dimension,
+{
+    fn deserialize<De>(deserializer: De) -> Result<Self, De::Error>
+    where
+        De: Deserializer<'de>,
+    {
+        let repr = Repr::<A, D, Ic>::deserialize(deserializer)?;
+        if !chunk_dim_fits::<D, C>(&repr.chunk_dim) {
+            return Err(De::Error::custom(
+                "chunk_dim does not fit in the coordinate type",
+            ));
+        }
+        let mut gridmap = Self::with_capacity(repr.chunk_dim, repr.chunks.len());
+
+        for (chunk_index, chunk) in repr.chunks {
+            // every chunk must have the dimensions declared by the gridmap
+            if chunk.shape() != repr.chunk_dim.as_slice() {
+                return Err(De::Error::custom("chunk shape does not match chunk_dim"));
+            }
+
+            // there is no point in keeping chunks without any cell
+            let chunk = Slot::new(Storage::Dense(chunk));
+            if chunk.occupied() == 0 {
+                continue;
+            }
+
+            if gridmap.map.insert(chunk_index, chunk).is_some() {
+                return Err(De::Error::custom("duplicate chunk index"));
+            }
+        }
+
+        Ok(gridmap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gridmap::bounding_box::BoundingBox;
+
+    #[test]
+    fn round_trip() {
+        let mut gridmap = GridMap::<u8, 2>::new([4, 4]);
+        gridmap.set(&[1, 2], 3);
+        gridmap.set(&[-7, 5], 9);
+        gridmap.fill(
+            &BoundingBox {
+                start: [8, -8],
+                end: [16, 0],
+            },
+            4,
+        );
+        gridmap.compress();
+
+        let json = serde_json::to_string(&gridmap).unwrap();
+        let decoded: GridMap<u8, 2> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, gridmap);
+        assert_eq!(decoded.chunk_dim(), &[4, 4]);
+    }
+
+    #[test]
+    fn null_chunks_are_skipped() {
+        let mut gridmap = GridMap::<u8, 2>::new([2, 2]);
+        gridmap.set(&[0, 0], 1);
+        gridmap.iter_mut().for_each(|cell| *cell = 0);
+
+        let json = serde_json::to_string(&gridmap).unwrap();
+        assert_eq!(json, r#"{"chunk_dim":[2,2],"chunks":[]}"#);
+    }
+
+    #[test]
+    fn reject_invalid_chunks() {
+        let chunk = r#"{"v":1,"dim":[2,3],"data":[1,0,0,0,0,0]}"#;
+        let json = format!(r#"{{"chunk_dim":[2,2],"chunks":[[[0,0],{chunk}]]}}"#);
+        let error = serde_json::from_str::<GridMap<u8, 2>>(&json).unwrap_err();
+        assert!(error.to_string().contains("chunk shape"));
+
+        let chunk = r#"{"v":1,"dim":[2,2],"data":[1,0,0,0]}"#;
+        let json = format!(r#"{{"chunk_dim":[2,2],"chunks":[[[0,0],{chunk}],[[0,0],{chunk}]]}}"#);
+        let error = serde_json::from_str::<GridMap<u8, 2>>(&json).unwrap_err();
+        assert!(error.to_string().contains("duplicate chunk"));
+
+        let json = format!(r#"{{"chunk_dim":[2,2],"chunks":[[[0,0],{chunk}]]}}"#);
+        let gridmap = serde_json::from_str::<GridMap<u8, 2>>(&json).unwrap();
+        assert_eq!(gridmap.get(&[0, 0]), 1);
+    }
+}
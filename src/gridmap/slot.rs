@@ -0,0 +1,189 @@
+//! Chunk stored in the GridMap along with its number of non-null cells
+
+use super::storage::Storage;
+use crate::{Chunk, cell::Cell};
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use ndarray::{Dim, Dimension, Ix};
+
+/// Number of non-null cells of a chunk which has not been counted yet
+const UNKNOWN: usize = usize::MAX;
+
+/// Chunk stored in the GridMap along with its number of non-null cells.
+/// Cloning a slot shares its cells, which are copied on the first write.
+pub(crate) struct Slot<A, const D: usize> {
+    /// Cells of the chunk, shared with the snapshots of the gridmap
+    storage: Arc<Storage<A, D>>,
+
+    /// Number of non-null cells, `UNKNOWN` if the cells may have been
+    /// modified through a mutable reference since it was last counted.
+    /// Atomic so that counting through a shared reference remembers the result.
+    occupied: AtomicUsize,
+}
+
+/// Share the cells and copy the number of non-null cells
+impl<A, const D: usize> Clone for Slot<A, D> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            storage: self.storage.clone(),
+            occupied: AtomicUsize::new(self.occupied.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl<A, const D: usize> Slot<A, D>
+where
+    A: Cell,
+    Dim<[Ix; D]>: Dimension,
+{
+    /// Wrap the storage and count its non-null cells
+    #[inline]
+    pub(crate) fn new(storage: Storage<A, D>) -> Self {
+        let occupied = storage.occupied();
+        Self {
+            storage: Arc::new(storage),
+            occupied: AtomicUsize::new(occupied),
+        }
+    }
+
+    /// Create a chunk with only null cells
+    #[inline]
+    pub(crate) fn empty(storage: Storage<A, D>) -> Self {
+        Self {
+            storage: Arc::new(storage),
+            occupied: AtomicUsize::new(0),
+        }
+    }
+
+    /// Access the cells of the chunk
+    #[inline]
+    pub(crate) fn storage(&self) -> &Storage<A, D> {
+        &self.storage
+    }
+
+    /// Take the cells of the chunk, copying them if they are shared
+    #[inline]
+    pub(crate) fn into_storage(self) -> Storage<A, D>
+    where
+        A: Clone,
+    {
+        Arc::unwrap_or_clone(self.storage)
+    }
+
+    /// Access the cells of the chunk as mutable, copying them if they are shared.
+    /// The number of non-null cells will have to be counted again.
+    #[inline]
+    pub(crate) fn storage_mut(&mut self) -> &mut Storage<A, D>
+    where
+        A: Clone,
+    {
+        *self.occupied.get_mut() = UNKNOWN;
+        Arc::make_mut(&mut self.storage)
+    }
+
+    /// Access the cells as a dense array to modify the non-null ones,
+    /// `None` if the chunk is known to hold only null cells.
+    #[inline]
+    pub(crate) fn occupied_mut(&mut self) -> Option<&mut Chunk<A, D>>
+    where
+        A: Clone,
+    {
+        let null = match self.storage() {
+            Storage::Uniform { cell, .. } => cell.is_null(),
+            _ => *self.occupied.get_mut() == 0,
+        };
+        if null {
+            None
+        } else {
+            Some(self.storage_mut().dense_mut())
+        }
+    }
+
+    /// Number of non-null cells, counting and remembering them if necessary
+    #[inline]
+    pub(crate) fn occupied(&self) -> usize {
+        match self.occupied.load(Ordering::Relaxed) {
+            UNKNOWN => {
+                let occupied = self.storage.occupied();
+                self.occupied.store(occupied, Ordering::Relaxed);
+                occupied
+            }
+            occupied => occupied,
+        }
+    }
+
+    /// Set a cell of the chunk and keep track of the number of non-null cells
+    pub(crate) fn set(&mut self, cell_index: &Dim<[Ix; D]>, cell: A)
+    where
+        A: PartialEq + Clone,
+    {
+        let occupied = self.occupied.get_mut();
+        if *occupied != UNKNOWN {
+            match (self.storage[*cell_index].is_null(), cell.is_null()) {
+                (true, false) => *occupied += 1,
+                (false, true) => *occupied -= 1,
+                _ => {}
+            }
+        }
+        Arc::make_mut(&mut self.storage).set(cell_index, cell);
+    }
+
+    /// Pick the representation using the least memory, the cells are left unchanged
+    #[inline]
+    pub(crate) fn compress(&mut self)
+    where
+        A: PartialEq + Clone,
+    {
+        // a shared chunk is only copied if its representation changes
+        if let Some(storage) = self.storage.compressed() {
+            self.storage = Arc::new(storage);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_is_remembered() {
+        let mut chunk = Slot::<u8, 2>::empty(Storage::new(&[4, 4]));
+        chunk.set(&Dim([1, 1]), 3);
+        chunk.set(&Dim([2, 1]), 4);
+        assert_eq!(chunk.occupied(), 2);
+
+        // modifying the cells through a reference forgets the count
+        chunk.storage_mut()[Dim([0, 0])] = 5;
+        assert_eq!(chunk.occupied.load(Ordering::Relaxed), UNKNOWN);
+        assert_eq!(chunk.occupied(), 3);
+        assert_eq!(chunk.occupied.load(Ordering::Relaxed), 3);
+
+        // then the count is kept up to date again
+        chunk.set(&Dim([1, 1]), 0);
+        assert_eq!(chunk.occupied.load(Ordering::Relaxed), 2);
+        assert_eq!(chunk.clone().occupied(), 2);
+    }
+
+    #[test]
+    fn clone_shares_the_cells() {
+        let mut chunk = Slot::<u8, 2>::empty(Storage::new(&[4, 4]));
+        chunk.set(&Dim([1, 1]), 3);
+        let mut copy = chunk.clone();
+        assert!(Arc::ptr_eq(&chunk.storage, &copy.storage));
+        assert_eq!(copy.occupied.load(Ordering::Relaxed), 1);
+
+        // the cells are copied on the first write
+        copy.set(&Dim([2, 2]), 4);
+        assert!(!Arc::ptr_eq(&chunk.storage, &copy.storage));
+        assert_eq!(chunk.occupied(), 1);
+        assert_eq!(copy.occupied(), 2);
+        assert_eq!(chunk.storage()[Dim([2, 2])], 0);
+
+        // an unknown count stays unknown in the clone
+        copy.storage_mut()[Dim([0, 0])] = 5;
+        let clone = copy.clone();
+        assert_eq!(clone.occupied.load(Ordering::Relaxed), UNKNOWN);
+        assert_eq!(clone.occupied(), 3);
+    }
+}
@@ -0,0 +1,161 @@
+//! Immutable snapshots of the GridMap sharing its chunks
+
+use super::GridMap;
+use crate::cell::Cell;
+use core::ops::Deref;
+
+/// Take snapshots of the gridmap
+impl<A, const D: usize, Ic, C> GridMap<A, D, Ic, C>
+where
+    A: Cell + Clone,
+    Ic: Clone,
+    C: Clone,
+{
+    /// Take an immutable snapshot of the gridmap, only the handles of the chunks are copied.
+    /// A chunk is copied the first time it is modified while a snapshot still shares it.
+    #[inline]
+    pub fn snapshot(&self) -> Snapshot<A, D, Ic, C> {
+        Snapshot {
+            gridmap: self.clone(),
+        }
+    }
+}
+
+/// Immutable state of a gridmap, sharing its chunks with it
+#[derive(Clone)]
+pub struct Snapshot<A, const D: usize, Ic = isize, C = isize>
+where
+    A: Cell,
+{
+    /// Gridmap holding the shared chunks
+    gridmap: GridMap<A, D, Ic, C>,
+}
+
+impl<A, const D: usize, Ic, C> Snapshot<A, D, Ic, C>
+where
+    A: Cell,
+{
+    /// Turn the snapshot into a gridmap which can be modified,
+    /// the chunks still shared are copied once they are modified.
+    #[inline]
+    pub fn into_gridmap(self) -> GridMap<A, D, Ic, C> {
+        self.gridmap
+    }
+}
+
+/// Read the gridmap
+impl<A, const D: usize, Ic, C> Deref for Snapshot<A, D, Ic, C>
+where
+    A: Cell,
+{
+    type Target = GridMap<A, D, Ic, C>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.gridmap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gridmap::{GridMap, bounding_box::BoundingBox};
+    use core::ptr;
+
+    /// Gridmap holding a dense chunk, a uniform chunk and a chunk of a single cell
+    fn sample() -> GridMap<u8, 2> {
+        let mut gridmap = GridMap::new([4, 4]);
+        for x in 0..4 {
+            gridmap.set(&[x, -1], x as u8 + 1);
+        }
+        gridmap.fill(
+            &BoundingBox {
+                start: [4, 4],
+                end: [8, 8],
+            },
+            9,
+        );
+        gridmap.set(&[-10, 10], 5);
+        gridmap
+    }
+
+    /// Returns true if both gridmaps share the chunk
+    fn shared(a: &GridMap<u8, 2>, b: &GridMap<u8, 2>, key: [isize; 2]) -> bool {
+        ptr::eq(a.map[&key].storage(), b.map[&key].storage())
+    }
+
+    #[test]
+    fn isolated_from_set_and_fill() {
+        let mut gridmap = sample();
+        let snapshot = gridmap.snapshot();
+
+        gridmap.set(&[0, -1], 7);
+        gridmap.fill(
+            &BoundingBox {
+                start: [5, 5],
+                end: [7, 7],
+            },
+            0,
+        );
+        gridmap.set(&[20, 20], 1);
+        assert_eq!(*snapshot, sample());
+        assert_eq!(snapshot.get(&[0, -1]), 1);
+        assert_eq!(snapshot.get(&[5, 5]), 9);
+        assert_eq!(gridmap.get(&[0, -1]), 7);
+
+        // the untouched chunk is still shared
+        assert!(!shared(&gridmap, &snapshot, [0, -1]));
+        assert!(!shared(&gridmap, &snapshot, [1, 1]));
+        assert!(shared(&gridmap, &snapshot, [-3, 2]));
+    }
+
+    #[test]
+    fn isolated_from_iter_mut() {
+        let mut gridmap = sample();
+        let snapshot = gridmap.snapshot();
+        gridmap.iter_mut().for_each(|cell| *cell += 1);
+        assert_eq!(*snapshot, sample());
+        assert_eq!(gridmap.get(&[-10, 10]), 6);
+        assert_eq!(gridmap.get(&[4, 4]), 10);
+        assert_eq!(snapshot.len(), gridmap.len());
+    }
+
+    #[test]
+    fn isolated_from_prune_and_compress() {
+        let mut gridmap = sample();
+        let bounds = BoundingBox {
+            start: [-10, 10],
+            end: [-9, 11],
+        };
+        gridmap
+            .bounded_iter_mut(bounds)
+            .for_each(|(_, cell)| *cell = 0);
+        let snapshot = gridmap.snapshot();
+        let chunks = snapshot.map.len();
+
+        gridmap.prune();
+        gridmap.compress();
+        assert_eq!(gridmap.map.len(), chunks - 1);
+        assert_eq!(snapshot.map.len(), chunks);
+        assert_eq!(*snapshot, gridmap);
+        // the uniform chunk is already compressed and stays shared
+        assert!(shared(&gridmap, &snapshot, [1, 1]));
+
+        // a gridmap taken back from the snapshot only copies the chunks it modifies
+        let mut copy = snapshot.clone().into_gridmap();
+        copy.set(&[3, -1], 0);
+        assert_eq!(snapshot.get(&[3, -1]), 4);
+        assert_eq!(gridmap.get(&[3, -1]), 4);
+        assert!(!shared(&copy, &snapshot, [0, -1]));
+        assert!(shared(&copy, &gridmap, [1, 1]));
+    }
+
+    #[test]
+    fn occupied_count_is_shared() {
+        let gridmap = sample();
+        let snapshot = gridmap.snapshot();
+        for (key, chunk) in &snapshot.map {
+            assert_eq!(chunk.occupied(), gridmap.map[key].occupied());
+        }
+        assert_eq!(snapshot.len(), 21);
+    }
+}
@@ -0,0 +1,433 @@
+//! Storage of the cells of a chunk
+
+/// Palette-compressed storage of the cells of a chunk
+pub mod palette;
+
+use crate::{Chunk, cell::Cell, util::is_chunk_empty};
+use core::ops::{Index, IndexMut, Range};
+use ndarray::{Array, Dim, Dimension, IntoDimension, Ix};
+use palette::Palette;
+
+/// Storage of the cells of a chunk
+#[derive(Clone)]
+pub enum Storage<A, const D: usize> {
+    /// Every cell is stored in a dense array
+    Dense(Chunk<A, D>),
+
+    /// Distinct cells are stored in a palette referenced by bit-packed indices
+    Palette(Palette<A, D>),
+
+    /// Every cell has the same value, no array is allocated
+    Uniform {
+        /// Dimensions of the chunk
+        shape: Dim<[Ix; D]>,
+
+        /// Value shared by every cell
+        cell: A,
+    },
+}
+
+impl<A, const D: usize> Storage<A, D> {
+    /// Create a chunk of null cells without allocating any array
+    #[inline]
+    pub fn new(chunk_dim: &[Ix; D]) -> Self
+    where
+        A: Cell,
+        [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    {
+        Self::uniform(chunk_dim, A::NULL)
+    }
+
+    /// Create a chunk where every cell has the given value
+    #[inline]
+    pub fn uniform(chunk_dim: &[Ix; D], cell: A) -> Self
+    where
+        [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    {
+        Self::Uniform {
+            shape: Dim(*chunk_dim),
+            cell,
+        }
+    }
+
+    /// Dimensions of the chunk
+    #[inline]
+    pub fn shape(&self) -> &[Ix]
+    where
+        Dim<[Ix; D]>: Dimension,
+    {
+        match self {
+            Self::Dense(chunk) => chunk.shape(),
+            Self::Palette(palette) => palette.shape(),
+            Self::Uniform { shape, .. } => shape.slice(),
+        }
+    }
+
+    /// Returns true if the chunk contains only null cells
+    pub fn is_empty(&self) -> bool
+    where
+        A: Cell,
+        Dim<[Ix; D]>: Dimension,
+    {
+        match self {
+            Self::Dense(chunk) => is_chunk_empty(chunk),
+            Self::Palette(palette) => {
+                palette.values().iter().all(Cell::is_null) || self.iter().all(Cell::is_null)
+            }
+            Self::Uniform { cell, .. } => cell.is_null(),
+        }
+    }
+
+    /// Number of non-null cells
+    pub fn occupied(&self) -> usize
+    where
+        A: Cell,
+        Dim<[Ix; D]>: Dimension,
+    {
+        match self {
+            Self::Uniform { shape, cell } => {
+                if cell.is_null() {
+                    0
+                } else {
+                    shape.size()
+                }
+            }
+            _ => self.iter().filter(|cell| !cell.is_null()).count(),
+        }
+    }
+
+    /// Set the cell at the given index.
+    /// A palette is widened to hold the new value or turned into
+    /// a dense array if it would end up taking more memory.
+    /// A uniform chunk is promoted on the first differing value.
+    pub fn set(&mut self, cell_index: &Dim<[Ix; D]>, cell: A)
+    where
+        A: PartialEq + Clone,
+        Dim<[Ix; D]>: Dimension,
+    {
+        match self {
+            Self::Dense(chunk) => chunk[*cell_index] = cell,
+            Self::Palette(palette) => {
+                palette.set(cell_index, cell);
+                if palette.memory() >= dense_memory::<A>(palette.shape()) {
+                    *self = Self::Dense(palette.to_dense());
+                }
+            }
+            Self::Uniform { shape, cell: value } => {
+                if *value != cell {
+                    // use the smallest representation for the number of cells
+                    let palette = Palette::filled(*shape, value.clone());
+                    *self = if palette.memory() < dense_memory::<A>(shape.slice()) {
+                        Self::Palette(palette)
+                    } else {
+                        Self::Dense(Array::from_elem(*shape, value.clone()))
+                    };
+                    self.set(cell_index, cell);
+                }
+            }
+        }
+    }
+
+    /// Access the cells as a dense array, converting the storage if necessary
+    pub fn dense_mut(&mut self) -> &mut Chunk<A, D>
+    where
+        A: Clone,
+        Dim<[Ix; D]>: Dimension,
+    {
+        match self {
+            Self::Dense(_) => {}
+            Self::Palette(palette) => *self = Self::Dense(palette.to_dense()),
+            Self::Uniform { shape, cell } => {
+                *self = Self::Dense(Array::from_elem(*shape, cell.clone()));
+            }
+        }
+        match self {
+            Self::Dense(chunk) => chunk,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Create a dense array with the same cells
+    pub fn to_dense(&self) -> Chunk<A, D>
+    where
+        A: Clone,
+        Dim<[Ix; D]>: Dimension,
+    {
+        match self {
+            Self::Dense(chunk) => chunk.clone(),
+            Self::Palette(palette) => palette.to_dense(),
+            Self::Uniform { shape, cell } => Array::from_elem(*shape, cell.clone()),
+        }
+    }
+
+    /// Convert into a dense array with the same cells
+    pub fn into_dense(self) -> Chunk<A, D>
+    where
+        A: Clone,
+        Dim<[Ix; D]>: Dimension,
+    {
+        match self {
+            Self::Dense(chunk) => chunk,
+            Self::Palette(palette) => palette.to_dense(),
+            Self::Uniform { shape, cell } => Array::from_elem(shape, cell),
+        }
+    }
+
+    /// Pick the representation using the least memory for the current cells
+    pub fn compress(&mut self)
+    where
+        A: PartialEq + Clone,
+        Dim<[Ix; D]>: Dimension,
+    {
+        if let Some(compressed) = self.compressed() {
+            *self = compressed;
+        }
+    }
+
+    /// Representation using the least memory for the current cells,
+    /// `None` if the current one is already the best.
+    pub(crate) fn compressed(&self) -> Option<Self>
+    where
+        A: PartialEq + Clone,
+        Dim<[Ix; D]>: Dimension,
+    {
+        // a chunk where every cell is the same does not need any array
+        let mut cells = self.iter();
+        if let Some(first) = cells.next()
+            && cells.all(|cell| cell == first)
+        {
+            let cell = first.clone();
+            let shape = match self {
+                Self::Dense(chunk) => chunk.raw_dim(),
+                Self::Palette(palette) => palette.raw_dim(),
+                Self::Uniform { .. } => return None,
+            };
+            return Some(Self::Uniform { shape, cell });
+        }
+
+        match self {
+            Self::Dense(chunk) => {
+                let palette = Palette::from_dense(chunk);
+                (palette.memory() < dense_memory::<A>(palette.shape()))
+                    .then_some(Self::Palette(palette))
+            }
+            Self::Palette(palette) => {
+                let mut shrunk = palette.clone();
+                shrunk.shrink();
+                if shrunk.memory() >= dense_memory::<A>(shrunk.shape()) {
+                    Some(Self::Dense(shrunk.to_dense()))
+                } else {
+                    (shrunk.values().len() < palette.values().len())
+                        .then_some(Self::Palette(shrunk))
+                }
+            }
+            Self::Uniform { .. } => None,
+        }
+    }
+
+    /// Iterate over the cells in logical order
+    pub fn iter(&self) -> Iter<'_, A, D>
+    where
+        Dim<[Ix; D]>: Dimension,
+    {
+        Iter(match self {
+            Self::Dense(chunk) => IterRepr::Dense(chunk.iter()),
+            Self::Palette(palette) => IterRepr::Palette {
+                palette,
+                positions: 0..palette.raw_dim().size(),
+            },
+            Self::Uniform { shape, cell } => IterRepr::Uniform {
+                cell,
+                remaining: shape.size(),
+            },
+        })
+    }
+
+    /// Iterate over the cells in logical order with their index in the chunk
+    pub fn indexed_iter(&self) -> IndexedIter<'_, A, D>
+    where
+        Dim<[Ix; D]>: Dimension,
+    {
+        IndexedIter(match self {
+            Self::Dense(chunk) => IndexedIterRepr::Dense(chunk.indexed_iter()),
+            Self::Palette(palette) => IndexedIterRepr::Palette {
+                palette,
+                indices: ndarray::indices(palette.raw_dim()).into_iter(),
+                position: 0,
+            },
+            Self::Uniform { shape, cell } => IndexedIterRepr::Uniform {
+                cell,
+                indices: ndarray::indices(*shape).into_iter(),
+            },
+        })
+    }
+}
+
+/// Access a cell of the chunk
+impl<A, const D: usize> Index<Dim<[Ix; D]>> for Storage<A, D>
+where
+    Dim<[Ix; D]>: Dimension,
+{
+    type Output = A;
+
+    #[inline]
+    fn index(&self, cell_index: Dim<[Ix; D]>) -> &Self::Output {
+        match self {
+            Self::Dense(chunk) => chunk.index(cell_index),
+            Self::Palette(palette) => palette.get(&cell_index),
+            Self::Uniform { cell, .. } => cell,
+        }
+    }
+}
+
+/// Mutably access a cell of the chunk.
+/// A palette or uniform chunk is converted to a dense array and stays dense
+/// until `compress` is called, use `set` to keep the compact representation.
+impl<A, const D: usize> IndexMut<Dim<[Ix; D]>> for Storage<A, D>
+where
+    A: Clone,
+    Dim<[Ix; D]>: Dimension,
+{
+    #[inline]
+    fn index_mut(&mut self, cell_index: Dim<[Ix; D]>) -> &mut Self::Output {
+        self.dense_mut().index_mut(cell_index)
+    }
+}
+
+/// Iterator over the cells of a chunk in logical order
+pub struct Iter<'i, A, const D: usize>(IterRepr<'i, A, D>);
+
+/// Iterator over the cells of each kind of storage
+enum IterRepr<'i, A, const D: usize> {
+    /// Iterate over a dense array
+    Dense(ndarray::iter::Iter<'i, A, Dim<[Ix; D]>>),
+
+    /// Iterate over the positions of the cells in a palette
+    Palette {
+        palette: &'i Palette<A, D>,
+        positions: Range<usize>,
+    },
+
+    /// Repeat the value of a uniform chunk
+    Uniform { cell: &'i A, remaining: usize },
+}
+
+/// Access next element of the iterator
+impl<'i, A, const D: usize> Iterator for Iter<'i, A, D>
+where
+    Dim<[Ix; D]>: Dimension,
+{
+    type Item = &'i A;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            IterRepr::Dense(cells) => cells.next(),
+            IterRepr::Palette { palette, positions } => {
+                positions.next().map(|position| palette.get_at(position))
+            }
+            IterRepr::Uniform { cell, remaining } => {
+                if *remaining == 0 {
+                    return None;
+                }
+                *remaining -= 1;
+                Some(*cell)
+            }
+        }
+    }
+}
+
+/// Iterator over the cells of a chunk in logical order with their index
+pub struct IndexedIter<'i, A, const D: usize>(IndexedIterRepr<'i, A, D>);
+
+/// Indexed iterator over the cells of each kind of storage
+enum IndexedIterRepr<'i, A, const D: usize> {
+    /// Iterate over a dense array
+    Dense(ndarray::iter::IndexedIter<'i, A, Dim<[Ix; D]>>),
+
+    /// Iterate over the indices of the cells in a palette
+    Palette {
+        palette: &'i Palette<A, D>,
+        indices: ndarray::iter::IndicesIter<Dim<[Ix; D]>>,
+        position: usize,
+    },
+
+    /// Repeat the value of a uniform chunk for each index
+    Uniform {
+        cell: &'i A,
+        indices: ndarray::iter::IndicesIter<Dim<[Ix; D]>>,
+    },
+}
+
+/// Access next element of the iterator
+impl<'i, A, const D: usize> Iterator for IndexedIter<'i, A, D>
+where
+    Dim<[Ix; D]>: Dimension,
+{
+    type Item = (<Dim<[Ix; D]> as Dimension>::Pattern, &'i A);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            IndexedIterRepr::Dense(cells) => cells.next(),
+            IndexedIterRepr::Palette {
+                palette,
+                indices,
+                position,
+            } => {
+                let index = indices.next()?;
+                let cell = palette.get_at(*position);
+                *position += 1;
+                Some((index, cell))
+            }
+            IndexedIterRepr::Uniform { cell, indices } => {
+                indices.next().map(|index| (index, *cell))
+            }
+        }
+    }
+}
+
+/// Number of bytes used by a dense array of the given dimensions
+#[inline]
+fn dense_memory<A>(chunk_dim: &[Ix]) -> usize {
+    chunk_dim.iter().product::<usize>() * size_of::<A>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_promotes() {
+        let mut storage = Storage::<u8, 2>::new(&[16, 16]);
+        storage.set(&Dim([0, 0]), 0);
+        assert!(matches!(storage, Storage::Uniform { .. }));
+
+        storage.set(&Dim([1, 2]), 5);
+        assert!(matches!(storage, Storage::Palette(_)));
+        assert_eq!(storage[Dim([1, 2])], 5);
+        assert_eq!(storage.occupied(), 1);
+
+        // the palette would not be smaller than a dense array of bytes
+        for cell in 0..=255 {
+            storage.set(&Dim([cell as usize / 16, cell as usize % 16]), cell);
+        }
+        assert!(matches!(storage, Storage::Dense(_)));
+        assert_eq!(storage.occupied(), 255);
+    }
+
+    #[test]
+    fn compress() {
+        let mut storage = Storage::<u32, 2>::uniform(&[8, 8], 3);
+        storage[Dim([2, 2])] = 3;
+        assert!(matches!(storage, Storage::Dense(_)));
+        storage.compress();
+        assert!(matches!(storage, Storage::Uniform { cell: 3, .. }));
+
+        storage[Dim([2, 2])] = 4;
+        storage.compress();
+        assert!(matches!(storage, Storage::Palette(_)));
+        assert_eq!(storage.iter().filter(|&&cell| cell == 4).count(), 1);
+        assert_eq!(storage.to_dense()[[2, 2]], 4);
+    }
+}
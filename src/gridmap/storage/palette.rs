@@ -0,0 +1,334 @@
+//! Palette-compressed storage of the cells of a chunk
+
+use crate::Chunk;
+use alloc::{vec, vec::Vec};
+use ndarray::{Array, Dim, Dimension, Ix};
+
+/// Number of bits in a word of packed indices
+const WORD_BITS: u32 = u64::BITS;
+
+/// Maximum number of bits used to store an index into the palette
+const MAX_BITS: u32 = 32;
+
+/// Chunk storing its distinct cells in a palette
+/// and the cells themselves as bit-packed indices into that palette.
+#[derive(Clone)]
+pub struct Palette<A, const D: usize> {
+    /// Dimensions of the chunk
+    shape: Dim<[Ix; D]>,
+
+    /// Distinct values of the cells
+    values: Vec<A>,
+
+    /// Number of bits used per index, always a power of two
+    bits: u32,
+
+    /// Bit-packed indices into the palette, in logical order
+    words: Vec<u64>,
+}
+
+impl<A, const D: usize> Palette<A, D>
+where
+    Dim<[Ix; D]>: Dimension,
+{
+    /// Create a palette where every cell has the given value
+    pub fn filled(shape: Dim<[Ix; D]>, value: A) -> Self {
+        Self {
+            shape,
+            values: vec![value],
+            bits: 0,
+            words: Vec::new(),
+        }
+    }
+
+    /// Build a palette from the cells of a dense chunk
+    pub fn from_dense(chunk: &Chunk<A, D>) -> Self
+    where
+        A: PartialEq + Clone,
+    {
+        // collect the distinct values and the index of each cell
+        let mut values: Vec<A> = Vec::new();
+        let mut indices = Vec::with_capacity(chunk.len());
+        for cell in chunk.iter() {
+            let index = match values.iter().position(|value| value == cell) {
+                Some(index) => index,
+                None => {
+                    values.push(cell.clone());
+                    values.len() - 1
+                }
+            };
+            indices.push(index);
+        }
+
+        let mut palette = Self {
+            shape: chunk.raw_dim(),
+            values,
+            bits: 0,
+            words: Vec::new(),
+        };
+        palette.pack(palette.values.len(), indices.into_iter());
+        palette
+    }
+
+    /// Create a dense chunk with the same cells
+    pub fn to_dense(&self) -> Chunk<A, D>
+    where
+        A: Clone,
+    {
+        let mut position = 0;
+        Array::from_shape_simple_fn(self.shape, || {
+            let cell = self.values[self.index_at(position)].clone();
+            position += 1;
+            cell
+        })
+    }
+
+    /// Dimensions of the chunk
+    #[inline]
+    pub fn shape(&self) -> &[Ix] {
+        self.shape.slice()
+    }
+
+    /// Dimensions of the chunk as a dimension object
+    #[inline]
+    pub fn raw_dim(&self) -> Dim<[Ix; D]> {
+        self.shape
+    }
+
+    /// Distinct values which may be referenced by the cells
+    #[inline]
+    pub fn values(&self) -> &[A] {
+        &self.values
+    }
+
+    /// Number of bits used to store each cell
+    #[inline]
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Approximate number of bytes used by the palette
+    #[inline]
+    pub fn memory(&self) -> usize {
+        self.words.len() * size_of::<u64>() + self.values.len() * size_of::<A>()
+    }
+
+    /// Access the cell at the given index
+    #[inline]
+    pub fn get(&self, cell_index: &Dim<[Ix; D]>) -> &A {
+        self.get_at(self.position(cell_index))
+    }
+
+    /// Access the cell at the given position in logical order
+    #[inline]
+    pub(crate) fn get_at(&self, position: usize) -> &A {
+        &self.values[self.index_at(position)]
+    }
+
+    /// Set the cell at the given index, widening the indices if the palette is full
+    pub fn set(&mut self, cell_index: &Dim<[Ix; D]>, value: A)
+    where
+        A: PartialEq,
+    {
+        let position = self.position(cell_index);
+
+        // reuse the value if it is already in the palette
+        if let Some(index) = self.values.iter().position(|v| *v == value) {
+            self.set_index_at(position, index);
+            return;
+        }
+
+        // make room for a new value
+        if self.values.len() >= self.capacity() {
+            self.rebuild(1);
+        }
+        self.values.push(value);
+        self.set_index_at(position, self.values.len() - 1);
+    }
+
+    /// Remove the values which are no longer used by any cell
+    pub fn shrink(&mut self) {
+        self.rebuild(0);
+    }
+
+    /// Number of cells in the chunk
+    #[inline]
+    fn count(&self) -> usize {
+        self.shape.size()
+    }
+
+    /// Number of values the palette can reference with the current indices
+    #[inline]
+    fn capacity(&self) -> usize {
+        1 << self.bits
+    }
+
+    /// Compute the position of a cell in logical order
+    #[inline]
+    fn position(&self, cell_index: &Dim<[Ix; D]>) -> usize {
+        let mut position = 0;
+        for d in 0..D {
+            debug_assert!(cell_index[d] < self.shape[d], "cell index out of chunk");
+            position = position * self.shape[d] + cell_index[d];
+        }
+        position
+    }
+
+    /// Read the index into the palette of the cell at the given position
+    #[inline]
+    fn index_at(&self, position: usize) -> usize {
+        if self.bits == 0 {
+            return 0;
+        }
+        let per_word = (WORD_BITS / self.bits) as usize;
+        let shift = (position % per_word) as u32 * self.bits;
+        let mask = u64::MAX >> (WORD_BITS - self.bits);
+        ((self.words[position / per_word] >> shift) & mask) as usize
+    }
+
+    /// Write the index into the palette of the cell at the given position
+    #[inline]
+    fn set_index_at(&mut self, position: usize, index: usize) {
+        if self.bits == 0 {
+            return;
+        }
+        let per_word = (WORD_BITS / self.bits) as usize;
+        let shift = (position % per_word) as u32 * self.bits;
+        let mask = u64::MAX >> (WORD_BITS - self.bits);
+        let word = &mut self.words[position / per_word];
+        *word = (*word & !(mask << shift)) | ((index as u64) << shift);
+    }
+
+    /// Drop the unused values and repack the indices
+    /// so that the palette can hold `extra` more values.
+    fn rebuild(&mut self, extra: usize) {
+        // find which values are still in use
+        let mut remap = vec![usize::MAX; self.values.len()];
+        for position in 0..self.count() {
+            remap[self.index_at(position)] = 0;
+        }
+
+        // compact the palette
+        let mut used = 0;
+        let mut index = 0;
+        self.values.retain(|_| {
+            let keep = remap[index] == 0;
+            if keep {
+                remap[index] = used;
+                used += 1;
+            }
+            index += 1;
+            keep
+        });
+
+        let indices: Vec<usize> = (0..self.count())
+            .map(|position| remap[self.index_at(position)])
+            .collect();
+        self.pack(self.values.len() + extra, indices.into_iter());
+    }
+
+    /// Pack the indices using enough bits to reference `count` values
+    fn pack(&mut self, count: usize, indices: impl Iterator<Item = usize>) {
+        // smallest power of two number of bits able to reference every value
+        let mut bits = 0;
+        while (1_usize << bits) < count {
+            bits = if bits == 0 { 1 } else { bits * 2 };
+        }
+        assert!(bits <= MAX_BITS, "too many values in the palette");
+        self.bits = bits;
+
+        if bits == 0 {
+            self.words = Vec::new();
+            return;
+        }
+        let per_word = (WORD_BITS / bits) as usize;
+        self.words = vec![0; self.count().div_ceil(per_word)];
+        for (position, index) in indices.enumerate() {
+            self.set_index_at(position, index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::IntoDimension;
+
+    fn chunk(cells: &[u8]) -> Chunk<u8, 2> {
+        Array::from_shape_vec([4, cells.len() / 4], cells.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn from_dense() {
+        let dense = chunk(&[0, 1, 2, 0, 0, 1, 2, 0, 3, 3, 3, 3, 0, 0, 0, 0]);
+        let palette = Palette::from_dense(&dense);
+        assert_eq!(palette.values(), &[0, 1, 2, 3]);
+        assert_eq!(palette.bits(), 2);
+        assert_eq!(palette.to_dense(), dense);
+        assert_eq!(*palette.get(&Dim([2, 1])), 3);
+
+        let palette = Palette::from_dense(&chunk(&[5; 8]));
+        assert_eq!(palette.bits(), 0);
+        assert_eq!(palette.memory(), size_of::<u8>());
+    }
+
+    #[test]
+    fn set_widens() {
+        let mut palette = Palette::filled(Dim([4, 4]), 0_u8);
+        let mut dense = chunk(&[0; 16]);
+        for (value, bits) in [(1, 1), (2, 2), (3, 2), (4, 4), (9, 4)] {
+            let index = Dim([value as usize % 4, value as usize / 4]);
+            palette.set(&index, value);
+            dense[index] = value;
+            assert_eq!(palette.bits(), bits);
+            assert_eq!(palette.to_dense(), dense);
+        }
+
+        // values already in the palette are reused
+        palette.set(&Dim([3, 3]), 4);
+        dense[[3, 3]] = 4;
+        assert_eq!(palette.values().len(), 6);
+        assert_eq!(palette.to_dense(), dense);
+    }
+
+    #[test]
+    fn set_reuses_unused_values() {
+        let mut palette = Palette::from_dense(&chunk(&[0, 1, 0, 0, 0, 0, 0, 0]));
+        assert_eq!(palette.bits(), 1);
+
+        // the palette is full, the unused value makes room for the new one
+        palette.set(&Dim([0, 1]), 0);
+        palette.set(&Dim([3, 1]), 7);
+        assert_eq!(palette.bits(), 1);
+        assert_eq!(palette.values(), &[0, 7]);
+        assert_eq!(palette.to_dense(), chunk(&[0, 0, 0, 0, 0, 0, 0, 7]));
+    }
+
+    #[test]
+    fn shrink() {
+        let cells: Vec<u8> = (0..16).collect();
+        let mut palette = Palette::from_dense(&chunk(&cells));
+        assert_eq!(palette.bits(), 4);
+
+        for index in ndarray::indices([4, 4]) {
+            palette.set(&index.into_dimension(), if index.0 < 2 { 3 } else { 12 });
+        }
+        palette.shrink();
+        assert_eq!(palette.values(), &[3, 12]);
+        assert_eq!(palette.bits(), 1);
+        assert_eq!(
+            palette.to_dense(),
+            chunk(&[3, 3, 3, 3, 3, 3, 3, 3, 12, 12, 12, 12, 12, 12, 12, 12])
+        );
+    }
+
+    #[test]
+    fn wide_indices() {
+        // more than 256 values need 16 bits per cell
+        let cells: Vec<u16> = (0..300).collect();
+        let dense = Array::from_shape_vec([20, 15], cells).unwrap();
+        let palette = Palette::from_dense(&dense);
+        assert_eq!(palette.bits(), 16);
+        assert_eq!(palette.to_dense(), dense);
+    }
+}
@@ -0,0 +1,296 @@
+//! Standard traits implemented by the GridMap
+
+use super::{
+    GridMap,
+    iterator::order::{ChunkOrder, Chunks},
+    slot::Slot,
+    storage::Storage,
+};
+use crate::{cell::Cell, coordinate::Coordinate};
+use alloc::vec::Vec;
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+};
+use hashbrown::{HashMap, hash_map::Entry};
+use ndarray::{Dim, Dimension, IntoDimension, Ix};
+use num_traits::{AsPrimitive, ConstZero, ToPrimitive};
+
+/// Summarize the gridmap without listing its cells
+impl<A, const D: usize, Ic, C> fmt::Debug for GridMap<A, D, Ic, C>
+where
+    A: Cell,
+    C: fmt::Debug,
+    Dim<[Ix; D]>: Dimension,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GridMap")
+            .field("chunk_dim", &self.chunk_dim)
+            .field("chunks", &self.map.len())
+            .field(
+                "cells",
+                &self.map.values().map(Slot::occupied).sum::<usize>(),
+            )
+            .field("order", &self.order)
+            .field("tracking_boundaries", &self.extents.is_some())
+            .field("limits", &self.limits)
+            .finish()
+    }
+}
+
+/// Two gridmaps are equal if they have the same chunk dimensions and the same non-null cells.
+/// Chunks with only null cells are ignored, as are the order, the limits and the capacity.
+impl<A, const D: usize, Ic, C> PartialEq for GridMap<A, D, Ic, C>
+where
+    A: Cell + PartialEq,
+    Ic: Eq + Hash,
+    Dim<[Ix; D]>: Dimension,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.chunk_dim == other.chunk_dim
+            && self
+                .map
+                .iter()
+                .all(|(key, chunk)| match other.map.get(key) {
+                    Some(other) => chunk.storage().iter().eq(other.storage().iter()),
+                    None => chunk.occupied() == 0,
+                })
+            && other
+                .map
+                .iter()
+                .all(|(key, chunk)| self.map.contains_key(key) || chunk.occupied() == 0)
+    }
+}
+
+impl<A, const D: usize, Ic, C> Eq for GridMap<A, D, Ic, C>
+where
+    A: Cell + Eq,
+    Ic: Eq + Hash,
+    Dim<[Ix; D]>: Dimension,
+{
+}
+
+/// Hash the non-null chunks in lexicographic order, consistently with the equality
+impl<A, const D: usize, Ic, C> Hash for GridMap<A, D, Ic, C>
+where
+    A: Cell + Hash,
+    Ic: Hash + AsPrimitive<isize>,
+    Dim<[Ix; D]>: Dimension,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.chunk_dim.hash(state);
+        for (key, chunk) in Chunks::new(&self.map, Some(ChunkOrder::Lexicographic))
+            .filter(|(_, chunk)| chunk.occupied() != 0)
+        {
+            key.hash(state);
+            chunk.storage().iter().for_each(|cell| cell.hash(state));
+        }
+    }
+}
+
+/// Collect cells into a gridmap with the default chunk dimensions,
+/// use `GridMap::new` then `extend` to pick the chunk dimensions.
+impl<A, const D: usize, Ic, C, I> FromIterator<([I; D], A)> for GridMap<A, D, Ic, C>
+where
+    A: Cell + PartialEq + Clone,
+    Ic: Eq + Hash + ConstZero + TryFrom<isize> + AsPrimitive<isize>,
+    C: Coordinate,
+    I: ToPrimitive + Copy,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = ([I; D], A)>,
+    {
+        let mut gridmap = Self::default();
+        gridmap.extend(iter);
+        gridmap
+    }
+}
+
+/// Set the cells in the order they are given, the last write to a cell wins
+impl<A, const D: usize, Ic, C, I> Extend<([I; D], A)> for GridMap<A, D, Ic, C>
+where
+    A: Cell + PartialEq + Clone,
+    Ic: Eq + Hash + ConstZero + TryFrom<isize> + AsPrimitive<isize>,
+    C: Coordinate,
+    I: ToPrimitive + Copy,
+    [Ix; D]: IntoDimension<Dim = Dim<[Ix; D]>>,
+    Dim<[Ix; D]>: Dimension,
+{
+    fn extend<T>(&mut self, iter: T)
+    where
+        T: IntoIterator<Item = ([I; D], A)>,
+    {
+        // group the writes by chunk so that each chunk is looked up only once
+        let mut writes: HashMap<_, Vec<_>> = HashMap::new();
+        for (index, cell) in iter {
+            let (chunk_index, cell_index) = self.split_index(&index);
+            writes
+                .entry(chunk_index)
+                .or_default()
+                .push((cell_index, cell));
+        }
+
+        for (chunk_index, cells) in writes {
+            // null cells have nothing to erase in a missing chunk
+            let mut entry = match self.map.entry(chunk_index) {
+                Entry::Occupied(entry) => entry,
+                Entry::Vacant(_) if cells.iter().all(|(_, cell)| cell.is_null()) => continue,
+                Entry::Vacant(entry) => {
+                    if let Some(extents) = &mut self.extents {
+                        extents.insert_chunk(entry.key());
+                    }
+                    entry.insert_entry(Slot::empty(Storage::new(&self.chunk_dim)))
+                }
+            };
+            if let Some(extents) = &mut self.extents {
+                for (cell_index, cell) in &cells {
+                    let null = cell.is_null();
+                    extents.set_cell(&self.chunk_dim, &self.shift, entry.key(), cell_index, null);
+                }
+            }
+
+            // set the cells
+            let chunk = entry.get_mut();
+            for (cell_index, cell) in cells {
+                chunk.set(&cell_index, cell);
+            }
+
+            // if the chunk end up empty, remove it from the map
+            if chunk.occupied() == 0 {
+                if let Some(extents) = &mut self.extents {
+                    extents.remove_chunk(entry.key());
+                }
+                entry.remove();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gridmap::{GridMap, bounding_box::BoundingBox, iterator::order::ChunkOrder};
+    use core::hash::BuildHasher;
+    use hashbrown::DefaultHashBuilder;
+
+    /// Gridmap holding a few cells around the origin
+    fn sample() -> GridMap<u8, 2> {
+        let mut gridmap = GridMap::new([4, 4]);
+        gridmap.set(&[-3, 2], 1);
+        gridmap.set(&[5, 5], 2);
+        gridmap.set(&[0, -9], 3);
+        gridmap
+    }
+
+    #[test]
+    fn null_chunks_are_ignored() {
+        let mut gridmap = sample();
+        gridmap.set(&[20, 20], 4);
+        gridmap.set(&[-20, -20], 5);
+        // the chunks are kept once all their cells are cleared in place
+        gridmap
+            .raw_iter_mut()
+            .filter(|cell| **cell > 3)
+            .for_each(|cell| *cell = 0);
+        assert_eq!(gridmap.map.len(), 5);
+
+        let sample = sample();
+        assert_eq!(gridmap, sample);
+        assert_eq!(sample, gridmap);
+
+        gridmap.set(&[20, 20], 4);
+        assert_ne!(gridmap, sample);
+        assert_ne!(sample, gridmap);
+    }
+
+    #[test]
+    fn hash_agrees_with_equality() {
+        let hasher = DefaultHashBuilder::default();
+        let sample = sample();
+
+        // same cells stored in other chunk layouts
+        let mut sparse = sample.clone();
+        sparse.set(&[40, -40], 9);
+        sparse
+            .bounded_iter_mut(BoundingBox {
+                start: [40, -40],
+                end: [41, -39],
+            })
+            .for_each(|(_, cell)| *cell = 0);
+        assert_eq!(sparse.map.len(), sample.map.len() + 1);
+        let mut compressed = sample.clone();
+        compressed.fill(
+            &BoundingBox {
+                start: [8, 8],
+                end: [16, 12],
+            },
+            6,
+        );
+        compressed.compress();
+        compressed
+            .raw_iter_mut()
+            .filter(|cell| **cell == 6)
+            .for_each(|cell| *cell = 0);
+        compressed.set_order(Some(ChunkOrder::Morton));
+        compressed.track_boundaries(true);
+        assert_eq!(compressed.map.len(), sample.map.len() + 2);
+        let collected: GridMap<u8, 2> = {
+            let mut gridmap = GridMap::new([4, 4]);
+            gridmap.extend([([5, 5], 2), ([0, -9], 3), ([-3, 2], 1)]);
+            gridmap
+        };
+
+        for other in [&sparse, &compressed, &collected] {
+            assert_eq!(*other, sample);
+            assert_eq!(hasher.hash_one(other), hasher.hash_one(&sample));
+        }
+
+        let mut other = sample.clone();
+        other.set(&[5, 5], 3);
+        assert_ne!(other, sample);
+        assert_ne!(hasher.hash_one(&other), hasher.hash_one(&sample));
+    }
+
+    #[test]
+    fn extend_clears_null_cells() {
+        let mut gridmap = sample();
+        gridmap.track_boundaries(true);
+        gridmap.extend([
+            ([-3, 2], 0),
+            ([5, 5], 7),
+            ([5, 5], 0),
+            ([6, 5], 8),
+            ([30, 30], 0),
+        ]);
+        assert_eq!(gridmap.get(&[-3, 2]), 0);
+        assert_eq!(gridmap.get(&[5, 5]), 0);
+        assert_eq!(gridmap.get(&[6, 5]), 8);
+        assert_eq!(gridmap.len(), 2);
+
+        // chunks left empty are freed and null cells allocate nothing
+        assert_eq!(gridmap.map.len(), 2);
+        assert_eq!(
+            gridmap.boundaries(),
+            Some(BoundingBox {
+                start: [0, -9],
+                end: [7, 6],
+            })
+        );
+
+        gridmap.extend([([0, -9], 0), ([6, 5], 0)]);
+        assert_eq!(gridmap.map.len(), 0);
+        assert_eq!(gridmap.boundaries(), None);
+    }
+
+    #[test]
+    fn collect_with_default_chunks() {
+        let gridmap: GridMap<u8, 2> = [([-3, 2], 1), ([5, 5], 2), ([0, -9], 3), ([40, 40], 0)]
+            .into_iter()
+            .collect();
+        assert_eq!(*gridmap.chunk_dim(), [12, 12]);
+        assert_eq!(gridmap.len(), 3);
+        assert_eq!(gridmap.rechunk([4, 4]), sample());
+    }
+}
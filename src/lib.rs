@@ -10,6 +10,9 @@ pub mod gridmap;
 /// Trait to implement to cells inserted in the gridmap
 pub mod cell;
 
+/// Trait to implement to the scalar type of the cell coordinates
+pub mod coordinate;
+
 /// Define how to transform a map
 pub mod transform;
 
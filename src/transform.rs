@@ -9,13 +9,18 @@ pub mod rotate;
 /// Translate the index
 pub mod translate;
 
+use crate::coordinate::Coordinate;
+
 /// Define a set of parameters to transform indexes
-pub trait Transform<const D: usize> {
+pub trait Transform<const D: usize, C = isize>
+where
+    C: Coordinate,
+{
     /// Apply a transformation to an index
-    fn apply(&self, index: &mut [isize; D]);
+    fn apply(&self, index: &mut [C; D]);
 
     /// Apply trasnformation to an index
-    fn transform(&self, index: &[isize; D]) -> [isize; D] {
+    fn transform(&self, index: &[C; D]) -> [C; D] {
         let mut new_index = *index;
         self.apply(&mut new_index);
         new_index
@@ -23,9 +28,12 @@ pub trait Transform<const D: usize> {
 }
 
 /// Apply a sequence of transformations to an index
-impl<const D: usize> Transform<D> for &[&dyn Transform<D>] {
+impl<const D: usize, C> Transform<D, C> for &[&dyn Transform<D, C>]
+where
+    C: Coordinate,
+{
     /// Apply a sequence of transformations to an index
-    fn apply(&self, index: &mut [isize; D]) {
+    fn apply(&self, index: &mut [C; D]) {
         for trs in self.iter() {
             trs.apply(index);
         }
@@ -1,13 +1,17 @@
 //! Mirror indexes
 
 use super::Transform;
+use crate::coordinate::Coordinate;
 
 /// Mirror the index
 pub struct Mirror<const D: usize>(pub [bool; D]);
 
 /// Apply the transformation to the given index
-impl<const D: usize> Transform<D> for Mirror<D> {
-    fn apply(&self, index: &mut [isize; D]) {
+impl<const D: usize, C> Transform<D, C> for Mirror<D>
+where
+    C: Coordinate,
+{
+    fn apply(&self, index: &mut [C; D]) {
         // flip the indexes
         for (d, i) in index.iter_mut().enumerate() {
             *i = if self.0[d] { -*i } else { *i };
@@ -1,6 +1,7 @@
 //! Rotate indexes
 
 use super::Transform;
+use crate::coordinate::Coordinate;
 
 /// Define the four possible orientations
 #[repr(u8)]
@@ -44,8 +45,11 @@ pub struct Rotate3 {
 }
 
 /// Apply the transformation to the given index
-impl Transform<2> for Rotate2 {
-    fn apply(&self, index: &mut [isize; 2]) {
+impl<C> Transform<2, C> for Rotate2
+where
+    C: Coordinate,
+{
+    fn apply(&self, index: &mut [C; 2]) {
         let [x, y] = *index;
 
         match self.0 {
@@ -73,8 +77,11 @@ impl Transform<2> for Rotate2 {
 }
 
 /// Apply the transformation to the given index
-impl Transform<3> for Rotate3 {
-    fn apply(&self, index: &mut [isize; 3]) {
+impl<C> Transform<3, C> for Rotate3
+where
+    C: Coordinate,
+{
+    fn apply(&self, index: &mut [C; 3]) {
         let [x, y, z] = *index;
 
         match (self.axis, self.rotation, self.left_handed) {
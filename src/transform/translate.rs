@@ -1,13 +1,17 @@
 //! Translate indexes
 
 use super::Transform;
+use crate::coordinate::Coordinate;
 
 /// Translate the index
-pub struct Translate<const D: usize>(pub [isize; D]);
+pub struct Translate<const D: usize, C = isize>(pub [C; D]);
 
 /// Apply the transformation to the given index
-impl<const D: usize> Transform<D> for Translate<D> {
-    fn apply(&self, index: &mut [isize; D]) {
+impl<const D: usize, C> Transform<D, C> for Translate<D, C>
+where
+    C: Coordinate,
+{
+    fn apply(&self, index: &mut [C; D]) {
         // flip the indexes
         for (d, i) in index.iter_mut().enumerate() {
             *i += self.0[d];
@@ -16,3 +16,13 @@ where
     }
     true
 }
+
+/// Compute the 32-bit FNV-1a hash of the given bytes
+pub(crate) fn fnv1a(bytes: &[u8]) -> u32 {
+    let mut hash = 0x811c_9dc5_u32;
+    for &byte in bytes {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash
+}